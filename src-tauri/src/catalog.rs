@@ -0,0 +1,65 @@
+// 门派与师门技能目录（与前端 src/data/schools.ts 保持一致）
+
+#[derive(Clone, serde::Serialize)]
+pub struct School {
+    pub name: &'static str,
+    pub master_skills: [&'static str; 7],
+}
+
+pub static SCHOOLS: [School; 18] = [
+    School { name: "大唐官府", master_skills: ["为官之道", "嗜血", "横扫千军", "无双一击", "神兵鉴赏", "紫薇之术", "文韬武略"] },
+    School { name: "龙宫", master_skills: ["九龙诀", "龙附", "逆鳞", "破浪诀", "呼风唤雨", "龙腾", "游龙术"] },
+    School { name: "普陀山", master_skills: ["灵性", "观音咒", "五行扭转", "金刚咒", "佛光普照", "普渡众生", "莲花妙法"] },
+    School { name: "化生寺", master_skills: ["小乘佛法", "金刚伏魔", "诵经", "佛光普照", "大慈大悲", "歧黄之术", "渡世行者"] },
+    School { name: "盘丝岭", master_skills: ["蛛丝", "含情脉脉", "催情大法", "秋波暗送", "天外魔音", "盘丝大法", "勾魂摄魄"] },
+    School { name: "魔王寨", master_skills: ["火云术", "牛虱阵", "震天诀", "叫嚣", "魔王护持", "三昧真火", "牛魔王门下"] },
+    School { name: "狮驼岭", master_skills: ["魔兽神功", "生死搏", "训兽诀", "阴阳二气诀", "狂兽诀", "大鹏展翅", "魔神附身"] },
+    School { name: "女儿村", master_skills: ["毒经", "轻如鸿毛", "飘渺式", "情天恨海", "闭月羞花", "沉鱼落雁", "倾国倾城"] },
+    School { name: "方寸山", master_skills: ["黄庭经", "磬龙灭法", "符之术", "归元心法", "五雷咒", "霹雳咒", "神道无念"] },
+    School { name: "阴曹地府", master_skills: ["灵通术", "幽冥鬼眼", "冤魂不散", "尸气漫天", "拘魂诀", "九幽阴魂", "慑人鬼魅"] },
+    School { name: "天宫", master_skills: ["清明自在", "乾坤塔", "混天术", "云霄步", "傲世诀", "乾坤袖", "雷霆天威"] },
+    School { name: "五庄观", master_skills: ["周易学", "潇湘仙雨", "修仙术", "混元道果", "太乙真诀", "乾坤袖", "太极生门"] },
+    School { name: "凌波城", master_skills: ["天地无极", "聚气", "气吞山河", "战意诀", "势如破竹", "风行云阵", "破云诀"] },
+    School { name: "无底洞", master_skills: ["地冥妙法", "怨念诀", "鬼蛊灵蕴", "摧心术", "夺命咒", "移魂化骨", "暗影诀"] },
+    School { name: "神木林", master_skills: ["巫咒", "万灵诸念", "天人庇护", "驭灵咒", "御血术", "巫咒结木", "万物之源"] },
+    School { name: "天机城", master_skills: ["神工无形", "攻云以守", "匠心不移", "运思如泉", "擎天之械", "千机奇巧", "探奥索隐"] },
+    School { name: "东海渊", master_skills: ["万物有灵", "逐波如意", "龟之象", "溯洄", "扶摇直上", "碧波之潮", "随波逐流"] },
+    School { name: "九黎城", master_skills: ["九黎战歌", "枫影二刃", "战魂", "破虚一击", "黎殇", "燃火", "祖巫之力"] },
+];
+
+pub fn find_school(name: &str) -> Option<&'static School> {
+    SCHOOLS.iter().find(|s| s.name == name)
+}
+
+pub fn is_known_school(name: &str) -> bool {
+    find_school(name).is_some()
+}
+
+pub fn is_master_skill_of(school: &str, skill_name: &str) -> bool {
+    find_school(school).map(|s| s.master_skills.contains(&skill_name)).unwrap_or(false)
+}
+
+// 修炼目录：人物修炼与宝宝修炼（控制力），default_mode 为界面默认的收费档位
+#[derive(Clone, serde::Serialize)]
+pub struct CultivationKind {
+    pub name: &'static str,
+    pub r#type: &'static str,
+    pub default_mode: &'static str,
+    pub max_level: i32,
+}
+
+pub static CULTIVATIONS: [CultivationKind; 8] = [
+    CultivationKind { name: "攻击修炼", r#type: "person", default_mode: "3w", max_level: 25 },
+    CultivationKind { name: "防御修炼", r#type: "person", default_mode: "2w", max_level: 25 },
+    CultivationKind { name: "法术修炼", r#type: "person", default_mode: "3w", max_level: 25 },
+    CultivationKind { name: "法抗修炼", r#type: "person", default_mode: "2w", max_level: 25 },
+    // 控制力上限未单独核实，沿用人物修炼的 25 级
+    CultivationKind { name: "攻击控制力", r#type: "pet", default_mode: "3w", max_level: 25 },
+    CultivationKind { name: "防御控制力", r#type: "pet", default_mode: "2w", max_level: 25 },
+    CultivationKind { name: "法术控制力", r#type: "pet", default_mode: "3w", max_level: 25 },
+    CultivationKind { name: "抗法控制力", r#type: "pet", default_mode: "2w", max_level: 25 },
+];
+
+pub fn find_cultivation(name: &str) -> Option<&'static CultivationKind> {
+    CULTIVATIONS.iter().find(|c| c.name == name)
+}
@@ -0,0 +1,249 @@
+// 累计表与单级表互转：识别输入是哪一种，换算出另一种，
+// 并交叉核对累计列（如修炼表的 cumMoney）与单级列之和、单级金钱与经验×单价是否一致。
+
+use crate::cost_overrides::LevelRow;
+use crate::costs::SkillLevelCost;
+use crate::rules;
+
+pub type Column = (&'static str, fn(&LevelRow) -> i64);
+
+#[derive(serde::Deserialize)]
+pub struct ConvertInput {
+    pub rows: Vec<LevelRow>,
+    // per_level / cumulative；不填时自动识别
+    pub form: Option<String>,
+    // master / assist / person_cultivation / pet_cultivation，填写后用内置表辅助识别
+    pub kind: Option<String>,
+    // 修炼表：每 10 点经验的价格与 money 列的单位（如按“万”录入填 10000），用于核对 money 列
+    pub price: Option<i64>,
+    pub money_unit: Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+pub struct Inconsistency {
+    pub level: i32,
+    pub column: String,
+    pub expected: i64,
+    pub actual: i64,
+    pub message: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct ConvertReport {
+    pub detected_form: String,
+    pub reason: String,
+    pub per_level: Vec<LevelRow>,
+    pub cumulative: Vec<LevelRow>,
+    pub issues: Vec<Inconsistency>,
+}
+
+pub fn to_per_level(rows: &[LevelRow]) -> Vec<LevelRow> {
+    let mut prev = LevelRow::default();
+    rows.iter().map(|r| {
+        let row = LevelRow { level: r.level, exp: r.exp - prev.exp, money: r.money - prev.money, gang: r.gang - prev.gang, ..Default::default() };
+        prev = *r;
+        row
+    }).collect()
+}
+
+pub fn to_cumulative(rows: &[LevelRow]) -> Vec<LevelRow> {
+    let mut sum = LevelRow::default();
+    rows.iter().map(|r| {
+        sum.exp += r.exp;
+        sum.money += r.money;
+        sum.gang += r.gang;
+        LevelRow { level: r.level, ..sum }
+    }).collect()
+}
+
+/// 与内置单级表逐格比较，返回（更接近内置累计值的格数, 参与比较的格数）
+pub fn closeness_to_cumulative(rows: &[LevelRow], reference: &[SkillLevelCost]) -> (usize, usize) {
+    let (mut closer, mut compared) = (0, 0);
+    let mut cum = SkillLevelCost::default();
+    for (row, base) in rows.iter().zip(reference) {
+        cum.exp += base.exp;
+        cum.money += base.money;
+        for (v, per, total) in [(row.exp, base.exp, cum.exp), (row.money, base.money, cum.money)] {
+            if per == total || v == 0 { continue; }
+            compared += 1;
+            if (v - total).abs() < (v - per).abs() { closer += 1; }
+        }
+    }
+    (closer, compared)
+}
+
+pub fn reference_table(kind: &str) -> Option<Vec<SkillLevelCost>> {
+    let builtin = rules::profile_of(rules::DEFAULT_SERVER_TYPE).builtin_cost_table();
+    let exp_only = |v: &[i64]| v.iter().map(|&exp| SkillLevelCost { exp, ..Default::default() }).collect();
+    match kind {
+        "master" => Some(builtin.master),
+        "assist" => Some(builtin.assist),
+        "person_cultivation" => Some(exp_only(&builtin.person_cultivation_exp)),
+        "pet_cultivation" => Some(exp_only(&builtin.pet_cultivation_exp)),
+        _ => None,
+    }
+}
+
+fn is_monotonic(rows: &[LevelRow]) -> bool {
+    rows.windows(2).all(|w| w[1].exp >= w[0].exp && w[1].money >= w[0].money && w[1].gang >= w[0].gang)
+}
+
+/// 识别表的形式：有任何一列下降的只能是单级表；其余借助内置表判断，无法判断时按单级处理
+pub fn detect_form(rows: &[LevelRow], kind: Option<&str>) -> (String, String) {
+    if !is_monotonic(rows) {
+        return ("per_level".to_string(), "存在下降的列，不可能是累计表".to_string());
+    }
+    if let Some(reference) = kind.and_then(reference_table) {
+        let (closer, compared) = closeness_to_cumulative(rows, &reference);
+        if compared >= 3 {
+            return if closer * 2 > compared {
+                ("cumulative".to_string(), format!("{}/{} 个数值更接近内置表的累计值", closer, compared))
+            } else {
+                ("per_level".to_string(), format!("{}/{} 个数值更接近内置表的单级值", compared - closer, compared))
+            };
+        }
+    }
+    ("per_level".to_string(), "各列单调不减，无法区分，按单级表处理".to_string())
+}
+
+// 累计列逐级核对：相邻两级之差应等于该级单级值
+fn check_cumulative_column(per_level: &[LevelRow], column: &str, per: fn(&LevelRow) -> i64, cum: fn(&LevelRow) -> Option<i64>, issues: &mut Vec<Inconsistency>) {
+    let mut prev: Option<i64> = Some(0);
+    for r in per_level {
+        if let (Some(p), Some(c)) = (prev, cum(r)) {
+            if c - p != per(r) {
+                issues.push(Inconsistency {
+                    level: r.level,
+                    column: column.to_string(),
+                    expected: p + per(r),
+                    actual: c,
+                    message: format!("{} 在 {}→{} 级增加 {}，而该级单级值为 {}", column, r.level - 1, r.level, c - p, per(r)),
+                });
+            }
+        }
+        prev = cum(r);
+    }
+}
+
+pub fn convert(input: &ConvertInput) -> Result<ConvertReport, String> {
+    if let Some(r) = input.rows.iter().enumerate().find(|(i, r)| r.level != *i as i32 + 1) {
+        return Err(format!("第 {} 行等级为 {}，等级需从 1 开始连续、不重复", r.0 + 1, r.1.level));
+    }
+    let (detected_form, reason) = detect_form(&input.rows, input.kind.as_deref());
+    let form = input.form.clone().unwrap_or_else(|| detected_form.clone());
+    let (per_level, cumulative) = match form.as_str() {
+        "per_level" => (input.rows.clone(), to_cumulative(&input.rows)),
+        "cumulative" => (to_per_level(&input.rows), input.rows.clone()),
+        other => return Err(format!("form 应为 per_level 或 cumulative，而不是 '{}'", other)),
+    };
+
+    let mut issues = Vec::new();
+    if form != detected_form {
+        issues.push(Inconsistency {
+            level: 0, column: "form".to_string(), expected: 0, actual: 0,
+            message: format!("标注为 {}，但识别结果为 {}（{}）", form, detected_form, reason),
+        });
+    }
+    if form == "cumulative" {
+        if let Some(r) = per_level.iter().find(|r| r.exp < 0 || r.money < 0 || r.gang < 0) {
+            issues.push(Inconsistency {
+                level: r.level, column: "form".to_string(), expected: 0, actual: 0,
+                message: format!("按累计表差分后 {} 级出现负数", r.level),
+            });
+        }
+    }
+    // 附带的累计列（cum_exp / cumMoney / cum_gang）与单级列之和核对
+    check_cumulative_column(&per_level, "cum_exp", |r| r.exp, |r| r.cum_exp, &mut issues);
+    check_cumulative_column(&per_level, "cum_money", |r| r.money, |r| r.cum_money, &mut issues);
+    check_cumulative_column(&per_level, "cum_gang", |r| r.gang, |r| r.cum_gang, &mut issues);
+    // 修炼表：单级金钱应等于 ceil(经验/10) × 单价
+    if let Some(price) = input.price {
+        let unit = input.money_unit.unwrap_or(1).max(1);
+        for r in &per_level {
+            let expected = (r.exp + 9) / 10 * price / unit;
+            if r.money != expected {
+                issues.push(Inconsistency {
+                    level: r.level, column: "money".to_string(), expected, actual: r.money,
+                    message: format!("{} 级经验 {} 按单价 {} 应为 {}，表中为 {}", r.level, r.exp, price, expected, r.money),
+                });
+            }
+        }
+    }
+    // 单级值明显偏离相邻两级（小于一半或大于两倍）时提示可能录入有误
+    let columns: [Column; 3] = [("exp", |r| r.exp), ("money", |r| r.money), ("gang", |r| r.gang)];
+    for (name, col) in columns {
+        for w in per_level.windows(3) {
+            let (a, v, b) = (col(&w[0]), col(&w[1]), col(&w[2]));
+            if a <= 0 || b <= 0 { continue; }
+            if v * 2 < a.min(b) || v > a.max(b) * 2 {
+                issues.push(Inconsistency {
+                    level: w[1].level, column: name.to_string(), expected: (a + b) / 2, actual: v,
+                    message: format!("{} 级 {} 为 {}，与相邻两级 {}、{} 相差过大", w[1].level, name, v, a, b),
+                });
+            }
+        }
+    }
+
+    Ok(ConvertReport { detected_form, reason, per_level, cumulative, issues })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(exp: &[i64]) -> Vec<LevelRow> {
+        exp.iter().enumerate().map(|(i, &exp)| LevelRow { level: i as i32 + 1, exp, ..Default::default() }).collect()
+    }
+
+    fn input(rows: Vec<LevelRow>, form: Option<&str>) -> ConvertInput {
+        ConvertInput { rows, form: form.map(str::to_string), kind: None, price: None, money_unit: None }
+    }
+
+    fn exp(rows: &[LevelRow]) -> Vec<i64> {
+        rows.iter().map(|r| r.exp).collect()
+    }
+
+    #[test]
+    fn per_level_and_cumulative_round_trip() {
+        let per = rows(&[10, 20, 30]);
+        let cum = to_cumulative(&per);
+        assert_eq!(exp(&cum), [10, 30, 60]);
+        assert_eq!(exp(&to_per_level(&cum)), [10, 20, 30]);
+    }
+
+    #[test]
+    fn detects_form_from_monotonicity_and_the_builtin_table() {
+        assert_eq!(detect_form(&rows(&[30, 20, 25]), None).0, "per_level");
+        let reference = reference_table("master").unwrap();
+        let per: Vec<LevelRow> = reference.iter().take(10).enumerate()
+            .map(|(i, c)| LevelRow { level: i as i32 + 1, exp: c.exp, money: c.money, ..Default::default() }).collect();
+        assert_eq!(detect_form(&per, Some("master")).0, "per_level");
+        assert_eq!(detect_form(&to_cumulative(&per), Some("master")).0, "cumulative");
+    }
+
+    #[test]
+    fn convert_reports_inconsistencies() {
+        assert!(convert(&input(rows(&[1, 2, 4]).into_iter().skip(1).collect(), None)).is_err());
+        assert!(convert(&input(rows(&[1, 2]), Some("monthly"))).is_err());
+
+        // 标注为累计但差分后出现负数
+        let report = convert(&input(rows(&[10, 30, 20]), Some("cumulative"))).unwrap();
+        assert!(report.issues.iter().any(|i| i.column == "form" && i.level == 3));
+
+        // 附带的累计列与单级之和不符
+        let mut with_cum = rows(&[10, 20, 30]);
+        with_cum[0].cum_exp = Some(10);
+        with_cum[1].cum_exp = Some(31);
+        let report = convert(&input(with_cum, Some("per_level"))).unwrap();
+        let issue = report.issues.iter().find(|i| i.column == "cum_exp").unwrap();
+        assert_eq!((issue.level, issue.expected, issue.actual), (2, 30, 31));
+
+        // 修炼表金钱按 ceil(经验/10) × 单价 核对
+        let mut priced = rows(&[15, 20]);
+        priced[0].money = 2 * 3;
+        priced[1].money = 5;
+        let report = convert(&ConvertInput { price: Some(3), ..input(priced, Some("per_level")) }).unwrap();
+        let money: Vec<_> = report.issues.iter().filter(|i| i.column == "money").map(|i| (i.level, i.expected)).collect();
+        assert_eq!(money, [(2, 6)]);
+    }
+}
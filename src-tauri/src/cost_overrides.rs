@@ -0,0 +1,258 @@
+// 用户自定义消耗表：从应用数据目录下的 cost_tables.json / cost_tables.toml 读取，
+// 覆盖内置表中给出的部分，未给出的部分沿用档案内置表。
+// 每张表可以按单级（per_level）或累计（cumulative）录入，累计表导入时差分为单级。
+
+use crate::cost_convert::{self, Column};
+use crate::costs::{CostTable, CultivationPrice, SkillLevelCost};
+use crate::dates;
+use crate::levels;
+use crate::rules;
+
+pub const FILE_STEM: &str = "cost_tables";
+pub const FILE_FORMATS: [&str; 2] = ["json", "toml"];
+
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct LevelRow {
+    pub level: i32,
+    #[serde(default)]
+    pub exp: i64,
+    #[serde(default)]
+    pub money: i64,
+    #[serde(default)]
+    pub gang: i64,
+    // 可选的累计列，仅用于换算时交叉核对
+    #[serde(default, alias = "cumExp", skip_serializing_if = "Option::is_none")]
+    pub cum_exp: Option<i64>,
+    #[serde(default, alias = "cumMoney", skip_serializing_if = "Option::is_none")]
+    pub cum_money: Option<i64>,
+    #[serde(default, alias = "cumGang", skip_serializing_if = "Option::is_none")]
+    pub cum_gang: Option<i64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TableInput {
+    // per_level 或 cumulative
+    #[serde(default = "default_form")]
+    pub form: String,
+    pub rows: Vec<LevelRow>,
+}
+
+fn default_form() -> String { "per_level".to_string() }
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct CostTableFile {
+    #[serde(default = "default_server_type")]
+    pub server_type: String,
+    pub version: Option<String>,
+    pub effective_date: Option<String>,
+    pub master: Option<TableInput>,
+    pub assist: Option<TableInput>,
+    // 修炼表只使用 exp 列
+    pub person_cultivation: Option<TableInput>,
+    pub pet_cultivation: Option<TableInput>,
+    pub cultivation_prices: Option<Vec<CultivationPrice>>,
+    // 人物升级经验，只使用 exp 列；第 n 行为升到 n 级所需经验
+    pub character: Option<TableInput>,
+}
+
+fn default_server_type() -> String { rules::DEFAULT_SERVER_TYPE.to_string() }
+
+#[derive(Default, serde::Serialize)]
+pub struct CostFileReport {
+    pub ok: bool,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// 按扩展名解析文件内容
+pub fn parse(content: &str, format: &str) -> Result<CostTableFile, String> {
+    match format {
+        "json" => serde_json::from_str(content).map_err(|e| format!("JSON 解析失败: {}", e)),
+        "toml" => toml::from_str(content).map_err(|e| format!("TOML 解析失败: {}", e)),
+        other => Err(format!("不支持的文件格式 '{}'，应为 json 或 toml", other)),
+    }
+}
+
+pub fn format_of(path: &std::path::Path) -> Result<String, String> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    if FILE_FORMATS.contains(&ext.as_str()) { Ok(ext) } else { Err(format!("不支持的文件格式 '{}'，应为 json 或 toml", ext)) }
+}
+
+fn check_table(label: &str, t: &TableInput, reference: &[SkillLevelCost], max_level: i32, report: &mut CostFileReport) {
+    if t.form != "per_level" && t.form != "cumulative" {
+        report.errors.push(format!("{}: form 应为 per_level 或 cumulative，而不是 '{}'", label, t.form));
+        return;
+    }
+    if t.rows.is_empty() {
+        report.errors.push(format!("{}: 没有任何数据行", label));
+        return;
+    }
+    for (i, row) in t.rows.iter().enumerate() {
+        let expect = i as i32 + 1;
+        if row.level != expect {
+            report.errors.push(format!("{}: 第 {} 行等级为 {}，应为 {}（等级需从 1 开始连续、不重复）", label, i + 1, row.level, expect));
+            return;
+        }
+        if row.exp < 0 || row.money < 0 || row.gang < 0 {
+            report.errors.push(format!("{}: {} 级存在负数", label, row.level));
+        }
+    }
+    if t.rows.len() as i32 > max_level {
+        report.warnings.push(format!("{}: 共 {} 级，超过上限 {} 的部分不会用到", label, t.rows.len(), max_level));
+    } else if t.rows.len() < reference.len() {
+        report.warnings.push(format!("{}: 只给出 1-{} 级，其余等级沿用内置表", label, t.rows.len()));
+    }
+    // 与内置单级表对照：多数行更接近内置表的累计值时，form 很可能标错了
+    let (closer_to_cumulative, compared) = cost_convert::closeness_to_cumulative(&t.rows, reference);
+    if compared >= 3 {
+        if t.form == "per_level" && closer_to_cumulative * 2 > compared {
+            report.warnings.push(format!("{}: 数值更接近内置表的累计值，可能应标为 cumulative", label));
+        } else if t.form == "cumulative" && closer_to_cumulative * 2 < compared {
+            report.warnings.push(format!("{}: 数值更接近内置表的单级值，可能应标为 per_level", label));
+        }
+    }
+    if t.form == "cumulative" {
+        // 累计表各列必须单调不减
+        let columns: [Column; 3] = [("exp", |r| r.exp), ("money", |r| r.money), ("gang", |r| r.gang)];
+        for (name, col) in columns {
+            if let Some(w) = t.rows.windows(2).find(|w| col(&w[1]) < col(&w[0])) {
+                report.errors.push(format!("{}: 累计表 {} 列在 {} 级处减少（{} → {}）", label, name, w[1].level, col(&w[0]), col(&w[1])));
+            }
+        }
+    }
+}
+
+pub fn validate(file: &CostTableFile) -> CostFileReport {
+    let mut report = CostFileReport::default();
+    let profile = match rules::find_profile(&file.server_type) {
+        Some(p) => p,
+        None => {
+            report.errors.push(format!("未知服务器类型 '{}'", file.server_type));
+            return report;
+        }
+    };
+    if let Some(d) = &file.effective_date {
+        if let Err(e) = dates::normalize_date(d) { report.errors.push(format!("effective_date: {}", e)); }
+    }
+    let builtin = profile.builtin_cost_table();
+    let exp_only = |v: &[i64]| -> Vec<SkillLevelCost> { v.iter().map(|&exp| SkillLevelCost { exp, ..Default::default() }).collect() };
+    if let Some(t) = &file.master { check_table("master", t, &builtin.master, profile.max_skill_level, &mut report); }
+    if let Some(t) = &file.assist { check_table("assist", t, &builtin.assist, profile.max_skill_level, &mut report); }
+    if let Some(t) = &file.person_cultivation {
+        check_table("person_cultivation", t, &exp_only(&builtin.person_cultivation_exp), profile.max_cultivation_level, &mut report);
+    }
+    if let Some(t) = &file.pet_cultivation {
+        check_table("pet_cultivation", t, &exp_only(&builtin.pet_cultivation_exp), profile.max_cultivation_level, &mut report);
+    }
+    if let Some(t) = &file.character {
+        check_table("character", t, &exp_only(&builtin.character_exp), levels::MAX_CHARACTER_LEVEL, &mut report);
+    }
+    if let Some(prices) = &file.cultivation_prices {
+        if prices.is_empty() { report.errors.push("cultivation_prices: 至少需要一个收费档位".to_string()); }
+        for p in prices {
+            if p.mode.trim().is_empty() { report.errors.push("cultivation_prices: 档位名称不能为空".to_string()); }
+            if p.price <= 0 { report.errors.push(format!("cultivation_prices: {} 的价格应大于 0", p.mode)); }
+        }
+    }
+    if file.master.is_none() && file.assist.is_none() && file.person_cultivation.is_none()
+        && file.pet_cultivation.is_none() && file.cultivation_prices.is_none() && file.character.is_none() {
+        report.warnings.push("文件没有覆盖任何表，将与内置表相同".to_string());
+    }
+    report.ok = report.errors.is_empty();
+    report
+}
+
+/// 转为单级表；累计表按相邻两级差分
+pub fn per_level_rows(t: &TableInput) -> Vec<SkillLevelCost> {
+    let rows = if t.form == "cumulative" { cost_convert::to_per_level(&t.rows) } else { t.rows.clone() };
+    rows.iter().map(|r| SkillLevelCost { exp: r.exp, money: r.money, gang: r.gang }).collect()
+}
+
+// 给出的等级替换内置表对应等级，更高的等级沿用内置表
+fn merge<T: Copy>(given: Vec<T>, base: &[T]) -> Vec<T> {
+    let n = given.len();
+    given.into_iter().chain(base.iter().skip(n).copied()).collect()
+}
+
+/// 以 base 为底，用文件中给出的表覆盖（调用前应先通过 validate）
+pub fn apply(file: &CostTableFile, mut base: CostTable) -> CostTable {
+    if let Some(t) = &file.master { base.master = merge(per_level_rows(t), &base.master); }
+    if let Some(t) = &file.assist { base.assist = merge(per_level_rows(t), &base.assist); }
+    if let Some(t) = &file.person_cultivation {
+        base.person_cultivation_exp = merge(per_level_rows(t).iter().map(|r| r.exp).collect(), &base.person_cultivation_exp);
+    }
+    if let Some(t) = &file.pet_cultivation {
+        base.pet_cultivation_exp = merge(per_level_rows(t).iter().map(|r| r.exp).collect(), &base.pet_cultivation_exp);
+    }
+    if let Some(p) = &file.cultivation_prices { base.cultivation_prices = p.clone(); }
+    if let Some(t) = &file.character {
+        base.character_exp = merge(per_level_rows(t).iter().map(|r| r.exp).collect(), &base.character_exp);
+    }
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(values: &[i64]) -> Vec<LevelRow> {
+        values.iter().enumerate().map(|(i, &v)| LevelRow { level: i as i32 + 1, exp: v, money: v * 2, ..Default::default() }).collect()
+    }
+
+    fn file(master: Option<TableInput>) -> CostTableFile {
+        CostTableFile {
+            server_type: rules::DEFAULT_SERVER_TYPE.to_string(), version: None, effective_date: None, master,
+            assist: None, person_cultivation: None, pet_cultivation: None, cultivation_prices: None, character: None,
+        }
+    }
+
+    #[test]
+    fn parses_json_and_toml() {
+        let json = parse(r#"{"master": {"form": "cumulative", "rows": [{"level": 1, "exp": 10, "cumMoney": 5}]}}"#, "json").unwrap();
+        let master = json.master.unwrap();
+        assert_eq!(json.server_type, rules::DEFAULT_SERVER_TYPE);
+        assert_eq!((master.form.as_str(), master.rows[0].exp, master.rows[0].cum_money), ("cumulative", 10, Some(5)));
+
+        let toml = parse("server_type = \"normal\"\n[[assist.rows]]\nlevel = 1\nexp = 3\n", "toml").unwrap();
+        let assist = toml.assist.unwrap();
+        assert_eq!((assist.form.as_str(), assist.rows[0].exp), ("per_level", 3));
+        assert!(parse("{}", "yaml").is_err());
+        assert!(format_of(std::path::Path::new("a/cost_tables.TOML")).is_ok());
+        assert!(format_of(std::path::Path::new("a/cost_tables.txt")).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_gaps_negatives_and_decreasing_cumulative_columns() {
+        let mut gap = rows(&[1, 2, 3]);
+        gap[2].level = 4;
+        let report = validate(&file(Some(TableInput { form: "per_level".into(), rows: gap })));
+        assert!(!report.ok && report.errors[0].contains("应为 3"));
+
+        let report = validate(&file(Some(TableInput { form: "per_level".into(), rows: rows(&[1, -2, 3]) })));
+        assert!(!report.ok && report.errors[0].contains("负数"));
+
+        let report = validate(&file(Some(TableInput { form: "cumulative".into(), rows: rows(&[5, 4, 6]) })));
+        assert!(!report.ok && report.errors.iter().any(|e| e.contains("减少")));
+
+        let mut unknown = file(None);
+        unknown.server_type = "unknown".into();
+        assert!(!validate(&unknown).ok);
+        let empty = validate(&file(None));
+        assert!(empty.ok && !empty.warnings.is_empty());
+    }
+
+    #[test]
+    fn apply_replaces_given_levels_and_keeps_the_rest() {
+        let base = rules::profile_of(rules::DEFAULT_SERVER_TYPE).builtin_cost_table();
+        let cumulative = TableInput { form: "cumulative".into(), rows: rows(&[10, 30, 60]) };
+        let f = file(Some(cumulative));
+        assert!(validate(&f).ok);
+        let merged = apply(&f, base.clone());
+        let exp: Vec<i64> = merged.master.iter().take(3).map(|r| r.exp).collect();
+        assert_eq!(exp, [10, 20, 30]);
+        assert_eq!(merged.master[1].money, 40);
+        assert_eq!(merged.master.len(), base.master.len());
+        assert_eq!(merged.master[3].exp, base.master[3].exp);
+        assert_eq!(merged.assist.len(), base.assist.len());
+    }
+}
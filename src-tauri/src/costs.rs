@@ -0,0 +1,236 @@
+// 消耗表与计算（与前端 src/data/*Costs.ts、src/utils/*Calculator.ts 同口径）
+// 各服务器使用哪一版消耗表由 rules::RuleProfile 与数据库中的 cost_tables 决定
+
+// 师门技能每级消耗（经验, 金钱），第 n 项为升到 n+1 级所需。
+// 前端 skillCosts.ts 虽标注为累计表，计算器实际按单级使用，这里与计算器一致。
+pub static MASTER_SKILL_COST: [(i64, i64); 180] = [
+    (0, 6), (0, 12), (0, 19), (0, 28), (0, 38),
+    (0, 51), (0, 67), (0, 86), (0, 110), (0, 139),
+    (0, 174), (0, 216), (0, 266), (0, 325), (0, 393),
+    (0, 472), (0, 563), (0, 667), (0, 786), (0, 919),
+    (0, 1070), (0, 1238), (0, 1426), (0, 1636), (0, 1868),
+    (0, 2124), (0, 2404), (0, 2714), (0, 3050), (0, 3420),
+    (10188, 3820), (11347, 4255), (12602, 4725), (13959, 5234), (15423, 5783),
+    (16998, 6374), (18692, 7009), (20508, 7690), (22452, 8419), (24532, 9199),
+    (26753, 10032), (29121, 10920), (31642, 11865), (34323, 12871), (37169, 13938),
+    (40188, 15070), (43388, 16270), (46773, 17540), (50352, 18882), (54132, 20299),
+    (58120, 21795), (62324, 23371), (66750, 25031), (71407, 26777), (76303, 28613),
+    (81444, 30541), (86840, 32565), (92500, 34687), (98430, 36911), (104640, 39240),
+    (111136, 41676), (117931, 44224), (125031, 46886), (132444, 49666), (140183, 52568),
+    (148253, 55595), (156666, 58749), (165430, 62036), (174556, 65458), (184052, 69019),
+    (193930, 72723), (204198, 76574), (214868, 80575), (225948, 84730), (237449, 89043),
+    (249383, 93518), (261760, 98160), (274589, 102971), (287884, 107956), (301652, 113119),
+    (315908, 118465), (330662, 123998), (345924, 129721), (361708, 135640), (378023, 141758),
+    (394882, 148080), (412297, 154611), (430280, 161355), (448844, 168316), (468000, 175500),
+    (487760, 182910), (508137, 190551), (529145, 198429), (550796, 206548), (573103, 214913),
+    (596078, 223529), (619735, 232400), (644088, 241533), (669149, 250931), (694932, 260599),
+    (721452, 270544), (748722, 280770), (776755, 291283), (805566, 302087), (835169, 313188),
+    (865579, 324592), (896809, 336303), (928876, 348328), (961792, 360672), (995572, 373339),
+    (1030234, 386337), (1065190, 399671), (1102256, 413346), (1139649, 427368), (1177983, 441743),
+    (1217273, 456477), (1256104, 471576), (1298787, 487045), (1341043, 502891), (1384320, 519120),
+    (1428632, 535737), (1473999, 552749), (1520435, 570163), (1567957, 587984), (1616583, 606218),
+    (1666328, 624873), (1717211, 643954), (1769248, 663468), (1822456, 683421), (1876852, 703819),
+    (1932456, 724671), (1989284, 745981), (2047353, 767757), (2106682, 790005), (2167289, 812733),
+    (2229192, 835947), (2292410, 859653), (2356960, 883860), (2422861, 908573), (2490132, 933799),
+    (2558792, 959547), (2628860, 985822), (2700356, 1012633), (2773296, 1039986), (2847703, 1067888),
+    (2923593, 1096347), (3000989, 1125371), (3079908, 1154965), (3160372, 1185139), (3242400, 1215900),
+    (6652022, 2494508), (6822452, 2558419), (6996132, 2623549), (7173104, 2689914), (7353406, 2757527),
+    (11305620, 4239607), (11586254, 4344845), (11872072, 4452027), (12163140, 4561177), (12459518, 4672319),
+    (15033471, 450041), (15315219, 4594563), (15600468, 4680138), (15889236, 4766769), (16181550, 4854465),
+    (16477425, 4943226), (16776885, 5033064), (17079954, 5123985), (17386650, 5215995), (17697000, 5309100),
+    (24014692, 7204407), (24438308, 7331490), (24866880, 7460064), (25300432, 7590129), (25739000, 7721700),
+    (32728255, 9818475), (33289095, 9986727), (33856310, 10156893), (34492930, 10328979), (40842000, 12252600),
+];
+
+// 辅助技能每级消耗（经验, 金钱, 帮贡）
+pub static ASSIST_SKILL_COST: [(i64, i64, i64); 159] = [
+    (16, 0, 1), (32, 5, 2), (52, 9, 3), (75, 14, 4),
+    (103, 19, 5), (136, 25, 6), (179, 33, 7), (231, 43, 8),
+    (295, 55, 9), (372, 69, 10), (466, 87, 11), (578, 108, 12),
+    (711, 133, 13), (867, 162, 14), (1049, 196, 15), (1260, 236, 16),
+    (1503, 281, 17), (1780, 333, 18), (2096, 393, 19), (2452, 459, 20),
+    (2854, 619, 21), (3807, 713, 22), (4364, 818, 23), (4983, 934, 24),
+    (5664, 1062, 25), (6415, 1202, 26), (7238, 1357, 27), (8138, 1525, 28),
+    (9120, 1710, 29), (10188, 1910, 30), (11347, 2127, 31), (12602, 2362, 32),
+    (13959, 2617, 33), (15423, 2891, 34), (16998, 3187, 35), (18629, 3504, 36),
+    (20508, 3845, 37), (22452, 4209, 38), (24532, 4599, 39), (26753, 5016, 40),
+    (29121, 5460, 41), (31642, 5932, 42), (34323, 6435, 43), (37169, 6969, 44),
+    (40188, 7535, 45), (43388, 8135, 46), (46773, 8770, 47), (50352, 9441, 48),
+    (54132, 10149, 49), (58120, 10897, 50), (62324, 11685, 51), (66750, 12515, 52),
+    (71407, 13388, 53), (76303, 14306, 54), (81444, 15270, 55), (86840, 16282, 56),
+    (92500, 17343, 57), (98430, 18455, 58), (104640, 19620, 59), (111136, 20838, 60),
+    (117931, 22112, 61), (25031, 23443, 62), (132444, 24833, 63), (140183, 26284, 64),
+    (148253, 27797, 65), (156666, 29374, 66), (165430, 31018, 67), (174556, 32729, 68),
+    (184052, 34509, 69), (193930, 36361, 70), (204198, 38287, 71), (214868, 40287, 72),
+    (225948, 42365, 73), (237449, 44521, 74), (249383, 46759, 75), (261760, 49080, 76),
+    (274589, 51485, 77), (287884, 53978, 78), (301652, 56559, 79), (315908, 59232, 80),
+    (330662, 61999, 81), (345924, 64860, 82), (361708, 67820, 83), (378023, 70879, 84),
+    (394882, 74040, 85), (412297, 77305, 86), (430280, 80677, 87), (448844, 84158, 88),
+    (468000, 87750, 89), (487760, 91455, 90), (508137, 95275, 91), (529145, 99214, 92),
+    (550796, 103274, 93), (573103, 107456, 94), (596078, 111764, 95), (619735, 116200, 96),
+    (644088, 120766, 97), (669149, 125465, 98), (694932, 130299, 99), (721452, 135272, 100),
+    (748722, 140385, 101), (776755, 145641, 102), (805566, 151043, 103), (835169, 156594, 104),
+    (865579, 162296, 105), (896809, 168151, 106), (928876, 174164, 107), (961792, 180336, 108),
+    (995572, 186669, 109), (1030234, 193168, 110), (1065190, 199835, 111), (1102256, 206673, 112),
+    (1139649, 213684, 113), (1177983, 220871, 114), (1217273, 228238, 115), (1256104, 235788, 116),
+    (1298787, 243522, 117), (1341043, 251445, 118), (1384320, 259560, 119), (1428632, 267868, 120),
+    (1473999, 276374, 121), (1520435, 285081, 122), (1567957, 293992, 123), (1616583, 303109, 124),
+    (1666328, 312436, 125), (1717211, 321977, 126), (1769248, 331734, 127), (1822456, 341710, 128),
+    (1876852, 351909, 129), (1932456, 362335, 130), (1989284, 372990, 131), (2047353, 383878, 132),
+    (2106682, 395002, 133), (2167289, 406366, 134), (2229192, 417973, 135), (2292410, 429826, 136),
+    (2356960, 441930, 137), (2422861, 454286, 138), (2490132, 466899, 139), (2558792, 479773, 140),
+    (2628860, 492911, 141), (2700356, 506316, 142), (2773296, 519993, 143), (2847703, 533944, 144),
+    (2923593, 548173, 145), (3000989, 562685, 146), (3079908, 577482, 147), (3160372, 592569, 148),
+    (3242400, 607950, 149), (6652022, 1247254, 150), (6822452, 1279209, 151), (6996132, 1311774, 152),
+    (7173104, 1344957, 153), (7353406, 1378763, 154), (11305620, 2119803, 155), (11586254, 2172422, 156),
+    (11872072, 2226013, 157), (12163140, 2280588, 158), (12459518, 2336159, 159),
+];
+
+// 修炼每级所需修炼经验（第 n 项为升到 n+1 级所需），每 10 点经验收费一次
+pub static PERSON_CULTIVATION_EXP: [i64; 25] = [
+    150, 210, 290, 390, 510, 650, 810, 990, 1190, 1410,
+    1650, 1910, 2190, 2490, 2810, 3150, 3510, 3890, 4290, 4710,
+    5150, 5610, 6090, 6590, 7110,
+];
+
+// 宝宝修炼没有单独核实过的经验表，内置档案直接共用 PERSON_CULTIVATION_EXP；
+// 消耗表里宝宝修炼仍是单独一列，可通过 import_cost_tables 导入实际数值
+pub static PET_CULTIVATION_EXP: &[i64] = &PERSON_CULTIVATION_EXP;
+
+// 人物升级经验，第 n 项为从 n-1 级升到 n 级所需。
+// 按 ⌊(24n³ + 180n − 2) / 5⌋ 推算：与 1-20 级的常见数值一致，更高等级未与游戏数据核对；
+// 消耗表的 character 列可通过 import_cost_tables 导入实际数值覆盖
+pub static CHARACTER_EXP: [i64; 175] = [
+    40, 110, 237, 450, 779, 1252, 1898, 2745, 3822, 5159,
+    6784, 8726, 11013, 13674, 16739, 20236, 24194, 28641, 33606, 39119,
+    45208, 51902, 59229, 67218, 75899, 85300, 95450, 106377, 118110, 130679,
+    144112, 158438, 173685, 189882, 207059, 225244, 244466, 264753, 286134, 308639,
+    332296, 357134, 383181, 410466, 439019, 468868, 500042, 532569, 566478, 601799,
+    638560, 676790, 716517, 757770, 800579, 844972, 890978, 938625, 987942, 1038959,
+    1091704, 1146206, 1202493, 1260594, 1320539, 1382356, 1446074, 1511721, 1579326, 1648919,
+    1720528, 1794182, 1869909, 1947738, 2027699, 2109820, 2194130, 2280657, 2369430, 2460479,
+    2553832, 2649518, 2747565, 2848002, 2950859, 3056164, 3163946, 3274233, 3387054, 3502439,
+    3620416, 3741014, 3864261, 3990186, 4118819, 4250188, 4384322, 4521249, 4660998, 4803599,
+    4949080, 5097470, 5248797, 5403090, 5560379, 5720692, 5884058, 6050505, 6220062, 6392759,
+    6568624, 6747686, 6929973, 7115514, 7304339, 7496476, 7691954, 7890801, 8093046, 8298719,
+    8507848, 8720462, 8936589, 9156258, 9379499, 9606340, 9836810, 10070937, 10308750, 10550279,
+    10795552, 11044598, 11297445, 11554122, 11814659, 12079084, 12347426, 12619713, 12895974, 13176239,
+    13460536, 13748894, 14041341, 14337906, 14638619, 14943508, 15252602, 15565929, 15883518, 16205399,
+    16531600, 16862150, 17197077, 17536410, 17880179, 18228412, 18581138, 18938385, 19300182, 19666559,
+    20037544, 20413166, 20793453, 21178434, 21568139, 21962596, 22361834, 22765881, 23174766, 23588519,
+    24007168, 24430742, 24859269, 25292778, 25731299,
+];
+
+#[derive(Clone, Copy, Default, serde::Serialize)]
+pub struct CultivationCost {
+    pub exp: i64,
+    pub money: i64,
+}
+
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct SkillLevelCost {
+    pub exp: i64,
+    pub money: i64,
+    #[serde(default)]
+    pub gang: i64,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct CultivationPrice {
+    pub mode: String,
+    // 每 10 点修炼经验的金钱价格
+    pub price: i64,
+}
+
+// 一版完整的消耗表，以 JSON 形式存入 cost_tables.data
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct CostTable {
+    pub master: Vec<SkillLevelCost>,
+    pub assist: Vec<SkillLevelCost>,
+    pub person_cultivation_exp: Vec<i64>,
+    pub pet_cultivation_exp: Vec<i64>,
+    pub cultivation_prices: Vec<CultivationPrice>,
+    // 人物升级经验，与修炼经验表一样第 n 项为升到 n 级所需经验
+    #[serde(default)]
+    pub character_exp: Vec<i64>,
+}
+
+/// 升到 level 级所需的经验（level 从 1 开始，超出表范围为 0）
+pub fn level_exp(table: &[i64], level: i32) -> i64 {
+    if level < 1 { return 0; }
+    table.get(level as usize - 1).copied().unwrap_or(0)
+}
+
+/// 按经验表与每 10 点经验单价，计算从 current_level（已积累 current_exp）升到 target_level 的消耗
+pub fn cultivation_cost(table: &[i64], unit: i64, current_level: i32, current_exp: i64, target_level: i32) -> CultivationCost {
+    let mut cost = CultivationCost::default();
+    if current_level < 0 || target_level <= current_level { return cost; }
+    for level in current_level + 1..=target_level {
+        let need = level_exp(table, level);
+        let remain = if level == current_level + 1 { (need - current_exp).max(0) } else { need };
+        cost.exp += remain;
+        cost.money += (remain + 9) / 10 * unit;
+    }
+    cost
+}
+
+/// 按单级表累加 current_level+1..=target_level 的消耗，超出表范围的等级按 0 计
+pub fn skill_cost(table: &[SkillLevelCost], current_level: i32, target_level: i32) -> SkillLevelCost {
+    let mut cost = SkillLevelCost::default();
+    if current_level < 0 || target_level <= current_level { return cost; }
+    for level in current_level + 1..=target_level {
+        if let Some(row) = table.get(level as usize - 1) {
+            cost.exp += row.exp;
+            cost.money += row.money;
+            cost.gang += row.gang;
+        }
+    }
+    cost
+}
+
+impl CostTable {
+    pub fn cultivation_exp_table(&self, r#type: &str) -> &[i64] {
+        if r#type == "pet" { &self.pet_cultivation_exp } else { &self.person_cultivation_exp }
+    }
+
+    pub fn cultivation_modes(&self) -> Vec<&str> {
+        self.cultivation_prices.iter().map(|p| p.mode.as_str()).collect()
+    }
+
+    pub fn cultivation_unit_price(&self, mode: &str) -> Option<i64> {
+        self.cultivation_prices.iter().find(|p| p.mode == mode).map(|p| p.price)
+    }
+
+    pub fn cultivation_level_exp(&self, r#type: &str, level: i32) -> i64 {
+        level_exp(self.cultivation_exp_table(r#type), level)
+    }
+
+    /// 本表不提供的收费档位按 0 计价（校验会先拒绝这类数据）
+    pub fn cultivation_cost(&self, r#type: &str, mode: &str, current_level: i32, current_exp: i64, target_level: i32) -> CultivationCost {
+        let unit = self.cultivation_unit_price(mode).unwrap_or(0);
+        cultivation_cost(self.cultivation_exp_table(r#type), unit, current_level, current_exp, target_level)
+    }
+
+    /// 从 (from_level, from_exp) 到 (to_level, to_exp) 的修炼消耗，用于按变更记录重新计算
+    pub fn cultivation_cost_between(&self, r#type: &str, mode: &str, from_level: i32, from_exp: i64, to_level: i32, to_exp: i64) -> CultivationCost {
+        let unit = self.cultivation_unit_price(mode).unwrap_or(0);
+        let (mut cost, partial) = if to_level > from_level {
+            (self.cultivation_cost(r#type, mode, from_level, from_exp, to_level), to_exp)
+        } else {
+            (CultivationCost::default(), to_exp - from_exp)
+        };
+        if partial > 0 {
+            cost.exp += partial;
+            cost.money += (partial + 9) / 10 * unit;
+        }
+        cost
+    }
+
+    pub fn master_cost(&self, current_level: i32, target_level: i32) -> SkillLevelCost {
+        skill_cost(&self.master, current_level, target_level)
+    }
+
+    pub fn assist_cost(&self, current_level: i32, target_level: i32) -> SkillLevelCost {
+        skill_cost(&self.assist, current_level, target_level)
+    }
+}
@@ -0,0 +1,142 @@
+// 日期解析与规范化：所有写入/查询的日期统一为 YYYY-MM-DD，
+// 这样 SQLite 中的字典序比较与 substr(date,1,7) 月份汇总才成立。
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 => if is_leap_year(year) { 29 } else { 28 },
+        _ => 0,
+    }
+}
+
+fn parse_part(s: &str) -> Option<u32> {
+    if s.is_empty() || s.len() > 4 || !s.chars().all(|c| c.is_ascii_digit()) { return None; }
+    s.parse().ok()
+}
+
+fn ymd(year: u32, month: u32, day: u32) -> Option<(i32, u32, u32)> {
+    let year = year as i32;
+    if !(2000..=2100).contains(&year) { return None; }
+    if !(1..=12).contains(&month) { return None; }
+    if day < 1 || day > days_in_month(year, month) { return None; }
+    Some((year, month, day))
+}
+
+/// 解析日期，接受 `YYYY-MM-DD`、`YYYY/M/D`、`YYYY.M.D`、`YYYYMMDD`，
+/// 以及带时间部分的写法（`2024-05-01 12:00:00`、`2024-05-01T12:00:00`，时间被丢弃）。
+pub fn parse_date(input: &str) -> Option<(i32, u32, u32)> {
+    let s = input.trim();
+    // 去掉时间部分
+    let s = s.split([' ', 'T']).next().unwrap_or("");
+    if s.len() == 8 && s.chars().all(|c| c.is_ascii_digit()) {
+        return ymd(s[0..4].parse().ok()?, s[4..6].parse().ok()?, s[6..8].parse().ok()?);
+    }
+    let parts: Vec<&str> = s.split(['-', '/', '.']).collect();
+    if parts.len() != 3 || parts[0].len() != 4 { return None; }
+    ymd(parse_part(parts[0])?, parse_part(parts[1])?, parse_part(parts[2])?)
+}
+
+// 公历日期与 1970-01-01 起的天数互转
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year } as i64;
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(z: i64) -> (i32, u32, u32) {
+    let z = z + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (yoe + era * 400) as i32 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// 两个日期相差的天数（end - start）
+pub fn days_between(start: &str, end: &str) -> Result<i64, String> {
+    let (sy, sm, sd) = parse_date(start).ok_or_else(|| format!("日期格式无效: '{}'，应为 YYYY-MM-DD", start))?;
+    let (ey, em, ed) = parse_date(end).ok_or_else(|| format!("日期格式无效: '{}'，应为 YYYY-MM-DD", end))?;
+    Ok(days_from_civil(ey, em, ed) - days_from_civil(sy, sm, sd))
+}
+
+/// 日期加减天数；结果超出 2000-2100 的支持范围时返回 None
+pub fn add_days(date: &str, days: i64) -> Option<String> {
+    let (y, m, d) = parse_date(date)?;
+    let (y, m, d) = civil_from_days(days_from_civil(y, m, d) + days);
+    if !(2000..=2100).contains(&y) { return None; }
+    Some(format_date(y, m, d))
+}
+
+pub fn format_date(year: i32, month: u32, day: u32) -> String {
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// 将输入规范化为 `YYYY-MM-DD`，无法识别时返回校验错误。
+pub fn normalize_date(input: &str) -> Result<String, String> {
+    parse_date(input)
+        .map(|(y, m, d)| format_date(y, m, d))
+        .ok_or_else(|| format!("日期格式无效: '{}'，应为 YYYY-MM-DD", input))
+}
+
+pub fn normalize_opt_date(input: Option<String>) -> Result<Option<String>, String> {
+    match input {
+        Some(s) if s.trim().is_empty() => Ok(None),
+        Some(s) => normalize_date(&s).map(Some),
+        None => Ok(None),
+    }
+}
+
+pub fn validate_year(year: i32) -> Result<(), String> {
+    if (2000..=2100).contains(&year) { Ok(()) } else { Err(format!("年份无效: {}", year)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_supported_formats() {
+        for input in ["2024-05-01", "2024/5/1", "2024.05.01", "20240501", " 2024-05-01 12:00:00", "2024-05-01T12:00:00"] {
+            assert_eq!(normalize_date(input).as_deref(), Ok("2024-05-01"), "{}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        for input in ["", "2024-13-01", "2023-02-29", "2024-04-31", "1999-12-31", "24-05-01", "2024-05", "2024-5-1-1", "abcd-05-01"] {
+            assert!(parse_date(input).is_none(), "{}", input);
+        }
+        assert_eq!(parse_date("2024-02-29"), Some((2024, 2, 29)));
+    }
+
+    #[test]
+    fn optional_dates_treat_blank_as_none() {
+        assert_eq!(normalize_opt_date(Some("  ".into())), Ok(None));
+        assert_eq!(normalize_opt_date(None), Ok(None));
+        assert_eq!(normalize_opt_date(Some("2024/1/2".into())), Ok(Some("2024-01-02".into())));
+        assert!(normalize_opt_date(Some("x".into())).is_err());
+    }
+
+    #[test]
+    fn day_arithmetic_crosses_months_and_leap_years() {
+        assert_eq!(days_between("2024-02-28", "2024-03-01"), Ok(2));
+        assert_eq!(days_between("2023-02-28", "2023-03-01"), Ok(1));
+        assert_eq!(days_between("2024-03-01", "2024-02-28"), Ok(-2));
+        assert_eq!(add_days("2024-12-31", 1).as_deref(), Some("2025-01-01"));
+        assert_eq!(add_days("2024-03-01", -1).as_deref(), Some("2024-02-29"));
+        assert_eq!(add_days("2100-12-31", 1), None);
+    }
+}
@@ -0,0 +1,57 @@
+// 数据变更事件：写操作成功后广播给所有窗口，便于多个账号窗口并排时同步刷新。
+// add_change_log 等操作会连带修改技能等级、账号余额与消耗记录，只按主实体发送一次；
+// 批量升级与撤销变更记录例外，逐行发送技能/修炼事件，并另发一次账号事件。
+
+use tauri::{AppHandle, Emitter};
+
+pub const DATA_CHANGED_EVENT: &str = "mhxy://data-changed";
+
+#[derive(Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Entity {
+    Account,
+    MasterSkill,
+    AssistSkill,
+    Cultivation,
+    Pet,
+    Equipment,
+    SpendLog,
+    ChangeLog,
+    SchoolChange,
+    CostTable,
+}
+
+#[derive(Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Create,
+    Update,
+    // 账号、技能、修炼为移入回收站
+    Delete,
+    // 从回收站恢复 / 彻底删除
+    Restore,
+    Purge,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct DataChanged {
+    pub entity: Entity,
+    pub action: Action,
+    // 不属于某个账号（如消耗表）或影响多个账号时为空
+    pub account_id: Option<i64>,
+    pub id: Option<i64>,
+}
+
+// 回收站与变更记录的类别对应的实体
+pub fn category_entity(category: &str) -> Entity {
+    match category {
+        "account" => Entity::Account,
+        "master" => Entity::MasterSkill,
+        "assist" => Entity::AssistSkill,
+        _ => Entity::Cultivation,
+    }
+}
+
+pub fn data_changed(app: &AppHandle, entity: Entity, action: Action, account_id: Option<i64>, id: Option<i64>) {
+    let _ = app.emit(DATA_CHANGED_EVENT, DataChanged { entity, action, account_id, id });
+}
@@ -0,0 +1,178 @@
+// 目标完成预测：以近期消耗记录（spend_logs）估算账号每天可投入的梦幻币，
+// 收入尚未单独记录，暂以历史实际投入代替。
+// 按周汇总后取第 25 / 75 百分位作为悲观 / 乐观速度，平均速度为总额除以天数。
+// 账号现有梦幻币先行投入；各目标按剩余消耗从低到高依次完成。
+
+use crate::dates;
+
+pub const DEFAULT_WINDOW_DAYS: i64 = 90;
+// 窗口内有消耗的天数少于此数时，速度只是粗略估计，预测标记为低可信度
+pub const MIN_ACTIVE_DAYS: i64 = 7;
+
+#[derive(Clone, Copy, Default, serde::Serialize)]
+pub struct GoldRate {
+    // 每天可投入的梦幻币
+    pub pessimistic: f64,
+    pub average: f64,
+    pub optimistic: f64,
+}
+
+#[derive(serde::Serialize)]
+pub struct DateBand {
+    // 按该速度无法完成或超出 2100 年时为空
+    pub optimistic: Option<String>,
+    pub average: Option<String>,
+    pub pessimistic: Option<String>,
+}
+
+// 参与预测的一项；remaining_gold 为到目标等级还需的梦幻币
+pub struct Target {
+    pub category: &'static str,
+    pub id: i64,
+    pub name: String,
+    pub current_level: i32,
+    pub target_level: i32,
+    pub remaining_gold: i64,
+}
+
+#[derive(serde::Serialize)]
+pub struct TargetForecast {
+    pub category: String,
+    pub id: i64,
+    pub name: String,
+    pub current_level: i32,
+    pub target_level: i32,
+    pub remaining_gold: i64,
+    // 按完成顺序累计到本项为止还需的梦幻币（已扣除现有余额）
+    pub cumulative_gold: i64,
+    pub eta: DateBand,
+}
+
+#[derive(serde::Serialize)]
+pub struct AccountForecast {
+    pub account_id: i64,
+    pub account_name: String,
+    pub gold_balance: i64,
+    // 统计区间为请求的完整窗口，没有消耗的日子按 0 计，不从首条记录起算以免高估速度
+    pub window_start: String,
+    pub window_end: String,
+    pub observed_days: i64,
+    pub observed_total: i64,
+    // 窗口内有消耗的天数
+    pub active_days: i64,
+    pub low_confidence: bool,
+    pub rate: GoldRate,
+    pub items: Vec<TargetForecast>,
+    pub all_targets: DateBand,
+}
+
+fn percentile(sorted: &[i64], p: f64) -> f64 {
+    if sorted.is_empty() { return 0.0; }
+    let pos = p * (sorted.len() - 1) as f64;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    sorted[lo] as f64 + (sorted[hi] - sorted[lo]) as f64 * (pos - lo as f64)
+}
+
+/// daily 为 (距区间起点的天数, 当天合计)，days 为区间天数
+pub fn gold_rate(daily: &[(i64, i64)], days: i64) -> GoldRate {
+    if days <= 0 { return GoldRate::default(); }
+    let weeks = ((days + 6) / 7) as usize;
+    let mut weekly = vec![0i64; weeks];
+    for &(offset, amount) in daily {
+        if (0..days).contains(&offset) { weekly[(offset / 7) as usize] += amount; }
+    }
+    // 最后一周不满 7 天时按实际天数折算，避免拉低速度
+    let tail = days - (weeks as i64 - 1) * 7;
+    if let Some(last) = weekly.last_mut() { *last = *last * 7 / tail; }
+    weekly.sort_unstable();
+    let total: i64 = daily.iter().filter(|(o, _)| (0..days).contains(o)).map(|(_, a)| a).sum();
+    GoldRate {
+        pessimistic: percentile(&weekly, 0.25) / 7.0,
+        average: total as f64 / days as f64,
+        optimistic: percentile(&weekly, 0.75) / 7.0,
+    }
+}
+
+fn eta(today: &str, need: i64, rate: f64) -> Option<String> {
+    if need <= 0 { return Some(today.to_string()); }
+    if rate <= 0.0 { return None; }
+    dates::add_days(today, (need as f64 / rate).ceil() as i64)
+}
+
+fn band(today: &str, need: i64, rate: &GoldRate) -> DateBand {
+    DateBand {
+        optimistic: eta(today, need, rate.optimistic),
+        average: eta(today, need, rate.average),
+        pessimistic: eta(today, need, rate.pessimistic),
+    }
+}
+
+/// 按剩余消耗从低到高排列目标，先用余额抵扣，再按速度推算完成日期
+pub fn schedule(today: &str, balance: i64, rate: &GoldRate, mut targets: Vec<Target>) -> (Vec<TargetForecast>, DateBand) {
+    targets.sort_by_key(|t| t.remaining_gold);
+    let mut cumulative = -balance.max(0);
+    let items: Vec<TargetForecast> = targets.into_iter().map(|t| {
+        cumulative += t.remaining_gold;
+        let need = cumulative.max(0);
+        TargetForecast {
+            category: t.category.to_string(), id: t.id, name: t.name,
+            current_level: t.current_level, target_level: t.target_level,
+            remaining_gold: t.remaining_gold, cumulative_gold: need,
+            eta: band(today, need, rate),
+        }
+    }).collect();
+    (items, band(today, cumulative.max(0), rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(id: i64, remaining_gold: i64) -> Target {
+        Target { category: "master", id, name: format!("技能{}", id), current_level: 1, target_level: 2, remaining_gold }
+    }
+
+    #[test]
+    fn gold_rate_spreads_over_the_whole_window() {
+        // 14 天内每天 700：三档速度相同
+        let daily: Vec<(i64, i64)> = (0..14).map(|d| (d, 700)).collect();
+        let rate = gold_rate(&daily, 14);
+        assert_eq!((rate.pessimistic, rate.average, rate.optimistic), (700.0, 700.0, 700.0));
+
+        // 只有一天有消耗时平均速度按整个窗口摊薄，区间外的记录不计
+        let rate = gold_rate(&[(0, 1400), (14, 9999), (-1, 9999)], 14);
+        assert_eq!(rate.average, 100.0);
+        assert_eq!(rate.pessimistic, 50.0);
+        assert_eq!(rate.optimistic, 150.0);
+        assert_eq!(gold_rate(&[(0, 100)], 0).average, 0.0);
+    }
+
+    #[test]
+    fn gold_rate_scales_a_partial_last_week() {
+        // 10 天：第二周只有 3 天，每天 70 折算为整周 490
+        let daily: Vec<(i64, i64)> = (0..10).map(|d| (d, 70)).collect();
+        let rate = gold_rate(&daily, 10);
+        assert_eq!((rate.pessimistic, rate.average, rate.optimistic), (70.0, 70.0, 70.0));
+    }
+
+    #[test]
+    fn schedule_orders_targets_and_uses_the_balance_first() {
+        let rate = GoldRate { pessimistic: 50.0, average: 100.0, optimistic: 200.0 };
+        let (items, all) = schedule("2026-01-01", 500, &rate, vec![target(1, 1000), target(2, 300)]);
+        let order: Vec<(i64, i64)> = items.iter().map(|i| (i.id, i.cumulative_gold)).collect();
+        assert_eq!(order, [(2, 0), (1, 800)]);
+        assert_eq!(items[0].eta.average.as_deref(), Some("2026-01-01"));
+        assert_eq!(items[1].eta.average.as_deref(), Some("2026-01-09"));
+        assert_eq!(all.optimistic.as_deref(), Some("2026-01-05"));
+        assert_eq!(all.pessimistic.as_deref(), Some("2026-01-17"));
+    }
+
+    #[test]
+    fn schedule_without_spend_has_no_eta() {
+        let (items, all) = schedule("2026-01-01", 0, &GoldRate::default(), vec![target(1, 10)]);
+        assert!(items[0].eta.average.is_none() && all.optimistic.is_none());
+        // 超出 2100 年的日期同样为空
+        let slow = GoldRate { pessimistic: 0.001, average: 0.001, optimistic: 0.001 };
+        assert!(schedule("2026-01-01", 0, &slow, vec![target(1, 1_000_000)]).1.average.is_none());
+    }
+}
@@ -0,0 +1,48 @@
+// 人物等级经验：升级所需经验取自消耗表的 character_exp（内置 costs::CHARACTER_EXP，可导入覆盖）；
+// 导入的表缺少某级数据时该级不给出进度，而不是按估算值展示。
+
+use crate::costs;
+
+pub const MAX_CHARACTER_LEVEL: i32 = 175;
+
+/// 当前等级升到下一级所需经验；已满级或消耗表没有该级数据时返回 None
+pub fn exp_to_level_up(table: &[i64], level: i32) -> Option<i64> {
+    if !(0..MAX_CHARACTER_LEVEL).contains(&level) { return None; }
+    Some(costs::level_exp(table, level + 1)).filter(|&need| need > 0)
+}
+
+// 满级时 next_level_exp 为空、其余为确定值；消耗表缺少该级数据时 known 为 false，其余均为空
+#[derive(serde::Serialize)]
+pub struct LevelProgress {
+    pub level: i32,
+    pub experience: i64,
+    pub known: bool,
+    pub next_level_exp: Option<i64>,
+    pub exp_to_next: Option<i64>,
+    pub can_level_up: Option<bool>,
+    pub progress_percent: Option<f64>,
+}
+
+pub fn level_progress(table: &[i64], level: i32, experience: i64) -> LevelProgress {
+    if level >= MAX_CHARACTER_LEVEL {
+        return LevelProgress {
+            level, experience, known: true, next_level_exp: None,
+            exp_to_next: Some(0), can_level_up: Some(false), progress_percent: Some(100.0),
+        };
+    }
+    match exp_to_level_up(table, level) {
+        Some(need) => LevelProgress {
+            level,
+            experience,
+            known: true,
+            next_level_exp: Some(need),
+            exp_to_next: Some((need - experience).max(0)),
+            can_level_up: Some(experience >= need),
+            progress_percent: Some((experience.max(0) as f64 / need as f64 * 100.0).min(100.0)),
+        },
+        None => LevelProgress {
+            level, experience, known: false, next_level_exp: None,
+            exp_to_next: None, can_level_up: None, progress_percent: None,
+        },
+    }
+}
@@ -1,77 +1,402 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod catalog;
+mod cost_convert;
+mod cost_overrides;
+mod costs;
+mod dates;
+mod events;
+mod forecast;
+mod levels;
+mod planner;
+mod rules;
 mod state;
+mod tasks;
+mod validation;
 
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
+use events::{data_changed, Action, Entity};
 use state::DbState;
+use tasks::{Progress, TaskProgress, Tasks};
+
+// 数据库操作放到阻塞线程池执行，不占用调用线程
+async fn blocking<T, F>(state: &DbState, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(DbState) -> Result<T, String> + Send + 'static,
+{
+    let db = state.clone();
+    tauri::async_runtime::spawn_blocking(move || f(db)).await.map_err(|e| e.to_string())?
+}
+
+// 长操作：传入 task_id 时通过事件报告进度，并可用 cancel_task 取消
+async fn blocking_task<T, F>(app: AppHandle, tasks: &Tasks, state: &DbState, task_id: Option<String>, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(DbState, &Progress) -> Result<T, String> + Send + 'static,
+{
+    let Some(id) = task_id else {
+        return blocking(state, move |db| f(db, &Progress::none())).await;
+    };
+    let running = tasks.start(&id)?;
+    let progress = Progress::new(move |stage, done, total| {
+        let _ = app.emit(tasks::PROGRESS_EVENT, TaskProgress { task_id: id.clone(), stage: stage.to_string(), done, total });
+    }, running.cancelled.clone());
+    // running 离开作用域时移除登记，await 中途被丢弃也不会残留
+    blocking(state, move |db| f(db, &progress)).await
+}
+
+#[tauri::command]
+fn cancel_task(
+    tasks: State<Tasks>,
+    task_id: Option<String>,
+    #[allow(non_snake_case)] taskId: Option<String>,
+) -> Result<bool, String> {
+    let id = task_id.or(taskId).ok_or_else(|| "missing task_id".to_string())?;
+    tasks.cancel(&id)
+}
+
+// 返回初始化时不影响启动的问题，界面逐条提示
+#[tauri::command]
+async fn db_init(state: State<'_, DbState>) -> Result<Vec<String>, String> {
+    blocking(&state, move |db| db.init()).await
+}
 
 #[tauri::command]
-fn db_init(state: State<DbState>) -> Result<(), String> {
-    state.init()
+async fn repair_dates(
+    app: AppHandle,
+    tasks: State<'_, Tasks>,
+    state: State<'_, DbState>,
+    task_id: Option<String>,
+    #[allow(non_snake_case)] taskId: Option<String>,
+) -> Result<state::DateRepairReport, String> {
+    let report = blocking_task(app.clone(), &tasks, &state, task_id.or(taskId), move |db, progress| db.repair_dates(progress)).await?;
+    for fix in &report.repaired {
+        let entity = if fix.table == "spend_logs" { Entity::SpendLog } else { Entity::ChangeLog };
+        data_changed(&app, entity, Action::Update, None, Some(fix.id));
+    }
+    Ok(report)
+}
+
+// Schools
+#[tauri::command]
+fn list_schools() -> Vec<catalog::School> {
+    catalog::SCHOOLS.to_vec()
+}
+
+#[tauri::command]
+fn get_school(name: String) -> Result<catalog::School, String> {
+    catalog::find_school(&name).cloned().ok_or_else(|| format!("未知门派: {}", name))
 }
 
 // Accounts
 #[tauri::command]
-fn add_account(state: State<DbState>, name: String, school: String, level: i32, experience: i64) -> Result<i64, String> {
-    state.add_account(name, school, level, experience)
+async fn add_account(app: AppHandle, state: State<'_, DbState>, account: state::NewAccount) -> Result<i64, String> {
+    let id = blocking(&state, move |db| db.add_account(account)).await?;
+    data_changed(&app, Entity::Account, Action::Create, Some(id), Some(id));
+    Ok(id)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn update_account(
+    app: AppHandle,
+    state: State<'_, DbState>,
+    id: i64,
+    name: String,
+    school: String,
+    server_type: Option<String>,
+    #[allow(non_snake_case)] serverType: Option<String>,
+    level: i32,
+    experience: i64,
+) -> Result<(), String> {
+    blocking(&state, move |db| db.update_account(id, name, school, server_type.or(serverType), level, experience)).await?;
+    data_changed(&app, Entity::Account, Action::Update, Some(id), Some(id));
+    Ok(())
+}
+
+#[tauri::command]
+fn list_rule_profiles() -> Vec<&'static rules::RuleProfile> {
+    rules::PROFILES.iter().collect()
+}
+
+#[tauri::command]
+async fn delete_account(app: AppHandle, state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    blocking(&state, move |db| db.delete_account(id)).await?;
+    data_changed(&app, Entity::Account, Action::Delete, Some(id), Some(id));
+    Ok(())
+}
+
+#[tauri::command]
+async fn change_school(
+    app: AppHandle,
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+    school: String,
+    strategy: Option<String>,
+    date: Option<String>,
+) -> Result<state::SchoolChange, String> {
+    let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
+    let change = blocking(&state, move |db| db.change_school(aid, school, strategy.unwrap_or_else(|| "map".to_string()), date)).await?;
+    data_changed(&app, Entity::SchoolChange, Action::Create, Some(aid), Some(change.id));
+    Ok(change)
+}
+
+#[tauri::command]
+async fn revert_school_change(app: AppHandle, state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("school_changes", id)?;
+        db.revert_school_change(id)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::SchoolChange, Action::Update, aid, Some(id));
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_school_changes(
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+) -> Result<Vec<state::SchoolChange>, String> {
+    let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
+    blocking(&state, move |db| db.get_school_changes(aid)).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn set_account_balances(
+    app: AppHandle,
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+    gold: Option<i64>,
+    experience: Option<i64>,
+    gang: Option<i64>,
+    cultivation_exp: Option<i64>,
+    #[allow(non_snake_case)] cultivationExp: Option<i64>,
+) -> Result<state::Resources, String> {
+    let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
+    let out = blocking(&state, move |db| db.set_account_balances(aid, gold, experience, gang, cultivation_exp.or(cultivationExp))).await?;
+    data_changed(&app, Entity::Account, Action::Update, Some(aid), Some(aid));
+    Ok(out)
+}
+
+#[tauri::command]
+async fn get_level_progress(
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+) -> Result<levels::LevelProgress, String> {
+    let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
+    blocking(&state, move |db| db.get_level_progress(aid)).await
+}
+
+#[tauri::command]
+async fn get_level_caps(
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+) -> Result<rules::LevelCaps, String> {
+    let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
+    blocking(&state, move |db| db.get_level_caps(aid)).await
+}
+
+#[tauri::command]
+async fn get_account_bundle(
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+) -> Result<state::AccountBundle, String> {
+    let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
+    blocking(&state, move |db| db.get_account_bundle(aid)).await
+}
+
+#[tauri::command]
+async fn get_overview(
+    app: AppHandle,
+    tasks: State<'_, Tasks>,
+    state: State<'_, DbState>,
+    task_id: Option<String>,
+    #[allow(non_snake_case)] taskId: Option<String>,
+) -> Result<Vec<state::AccountOverview>, String> {
+    blocking_task(app, &tasks, &state, task_id.or(taskId), move |db, progress| db.get_overview(progress)).await
+}
+
+#[tauri::command]
+async fn get_account_remaining_cost(
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+) -> Result<state::RemainingCost, String> {
+    let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
+    blocking(&state, move |db| db.get_account_remaining_cost(aid)).await
+}
+
+#[tauri::command]
+async fn get_all_remaining_costs(
+    app: AppHandle,
+    tasks: State<'_, Tasks>,
+    state: State<'_, DbState>,
+    task_id: Option<String>,
+    #[allow(non_snake_case)] taskId: Option<String>,
+) -> Result<state::FleetRemainingCost, String> {
+    blocking_task(app, &tasks, &state, task_id.or(taskId), move |db, progress| db.get_all_remaining_costs(progress)).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn plan_upgrades(
+    app: AppHandle,
+    tasks: State<'_, Tasks>,
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+    request: planner::PlanRequest,
+    task_id: Option<String>,
+    #[allow(non_snake_case)] taskId: Option<String>,
+) -> Result<planner::UpgradePlan, String> {
+    let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
+    blocking_task(app, &tasks, &state, task_id.or(taskId), move |db, progress| db.plan_upgrades(aid, request, progress)).await
+}
+
+#[tauri::command]
+async fn reach_with_budget(state: State<'_, DbState>, category: String, id: i64, available: planner::Available) -> Result<planner::Reach, String> {
+    blocking(&state, move |db| db.reach_with_budget(category, id, available)).await
+}
+
+// 不传 account_id 时预测全部账号
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn forecast_targets(
+    app: AppHandle,
+    tasks: State<'_, Tasks>,
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+    days: Option<i64>,
+    task_id: Option<String>,
+    #[allow(non_snake_case)] taskId: Option<String>,
+) -> Result<Vec<forecast::AccountForecast>, String> {
+    blocking_task(app, &tasks, &state, task_id.or(taskId), move |db, progress| db.forecast_targets(account_id.or(accountId), days, progress)).await
+}
+
+#[tauri::command]
+async fn character_exp_table(state: State<'_, DbState>, server_type: Option<String>) -> Result<Vec<i64>, String> {
+    blocking(&state, move |db| db.character_exp_table(server_type)).await
 }
 
 #[tauri::command]
-fn update_account(state: State<DbState>, id: i64, name: String, school: String, level: i32, experience: i64) -> Result<(), String> {
-    state.update_account(id, name, school, level, experience)
+async fn get_all_accounts(state: State<'_, DbState>) -> Result<Vec<state::Account>, String> {
+    blocking(&state, move |db| db.get_all_accounts()).await
 }
 
+// Trash
 #[tauri::command]
-fn delete_account(state: State<DbState>, id: i64) -> Result<(), String> {
-    state.delete_account(id)
+async fn list_trash(
+    app: AppHandle,
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+) -> Result<Vec<state::TrashItem>, String> {
+    let purged = blocking(&state, move |db| db.purge_expired_trash()).await?;
+    trash_purged(&app, &purged);
+    blocking(&state, move |db| db.list_trash(account_id.or(accountId))).await
+}
+
+#[tauri::command]
+async fn restore_from_trash(app: AppHandle, state: State<'_, DbState>, category: String, id: i64) -> Result<(), String> {
+    let entity = events::category_entity(&category);
+    let aid = blocking(&state, move |db| db.restore_from_trash(category, id)).await?;
+    data_changed(&app, entity, Action::Restore, Some(aid), Some(id));
+    Ok(())
 }
 
 #[tauri::command]
-fn get_all_accounts(state: State<DbState>) -> Result<Vec<state::Account>, String> {
-    state.get_all_accounts()
+async fn purge_from_trash(app: AppHandle, state: State<'_, DbState>, category: String, id: i64) -> Result<(), String> {
+    let entity = events::category_entity(&category);
+    let aid = blocking(&state, move |db| db.purge_from_trash(category, id)).await?;
+    data_changed(&app, entity, Action::Purge, Some(aid), Some(id));
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_trash_retention_days(state: State<'_, DbState>) -> Result<i64, String> {
+    blocking(&state, move |db| db.get_trash_retention_days()).await
+}
+
+// 返回因新设置而立即清理的记录数
+#[tauri::command]
+async fn set_trash_retention_days(app: AppHandle, state: State<'_, DbState>, days: i64) -> Result<usize, String> {
+    let purged = blocking(&state, move |db| db.set_trash_retention_days(days)).await?;
+    Ok(trash_purged(&app, &purged))
+}
+
+// 过期清理可能涉及多个账号，按类别各发送一次事件，返回清理总数
+fn trash_purged(app: &AppHandle, purged: &[(&str, usize)]) -> usize {
+    for (category, _) in purged {
+        data_changed(app, events::category_entity(category), Action::Purge, None, None);
+    }
+    purged.iter().map(|p| p.1).sum()
 }
 
 // Spend logs & gold
 #[tauri::command]
-fn add_spend_log(
-    state: State<DbState>,
+#[allow(clippy::too_many_arguments)]
+async fn add_spend_log(
+    app: AppHandle,
+    state: State<'_, DbState>,
     account_id: Option<i64>,
     #[allow(non_snake_case)] accountId: Option<i64>,
     amount: i64,
     date: String,
     note: Option<String>,
+    pet_id: Option<i64>,
+    #[allow(non_snake_case)] petId: Option<i64>,
+    equipment_id: Option<i64>,
+    #[allow(non_snake_case)] equipmentId: Option<i64>,
 ) -> Result<i64, String> {
     let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
-    state.add_spend_log(aid, amount, date, note)
+    let id = blocking(&state, move |db| db.add_spend_log(aid, amount, date, note, pet_id.or(petId), equipment_id.or(equipmentId))).await?;
+    data_changed(&app, Entity::SpendLog, Action::Create, Some(aid), Some(id));
+    Ok(id)
 }
 
 #[tauri::command]
-fn get_spend_logs(
-    state: State<DbState>,
+#[allow(clippy::too_many_arguments)]
+async fn get_spend_logs(
+    state: State<'_, DbState>,
     account_id: Option<i64>,
     #[allow(non_snake_case)] accountId: Option<i64>,
     start: Option<String>,
     end: Option<String>,
+    pet_id: Option<i64>,
+    #[allow(non_snake_case)] petId: Option<i64>,
+    equipment_id: Option<i64>,
+    #[allow(non_snake_case)] equipmentId: Option<i64>,
 ) -> Result<Vec<state::SpendLog>, String> {
     let aid = account_id.or(accountId);
-    state.get_spend_logs(aid, start, end)
+    blocking(&state, move |db| db.get_spend_logs(aid, start, end, pet_id.or(petId), equipment_id.or(equipmentId))).await
 }
 
 #[tauri::command]
-fn get_spend_summary_daily(state: State<DbState>, start: String, end: String) -> Result<Vec<state::SpendSummary>, String> {
-    state.get_spend_summary_daily(start, end)
+async fn get_spend_summary_daily(state: State<'_, DbState>, start: String, end: String) -> Result<Vec<state::SpendSummary>, String> {
+    blocking(&state, move |db| db.get_spend_summary_daily(start, end)).await
 }
 
 #[tauri::command]
-fn get_spend_summary_monthly(state: State<DbState>, year: i32) -> Result<Vec<state::SpendSummary>, String> {
-    state.get_spend_summary_monthly(year)
+async fn get_spend_summary_monthly(state: State<'_, DbState>, year: i32) -> Result<Vec<state::SpendSummary>, String> {
+    blocking(&state, move |db| db.get_spend_summary_monthly(year)).await
 }
 
 // Change logs
 #[tauri::command]
-fn add_change_log(
-    state: State<DbState>,
+#[allow(clippy::too_many_arguments)]
+async fn add_change_log(
+    app: AppHandle,
+    state: State<'_, DbState>,
     account_id: Option<i64>,
     #[allow(non_snake_case)] accountId: Option<i64>,
     category: String,
@@ -84,84 +409,199 @@ fn add_change_log(
     #[allow(non_snake_case)] fromExp: Option<i32>,
     to_exp: Option<i32>,
     #[allow(non_snake_case)] toExp: Option<i32>,
-    consumed_exp: Option<i64>,
-    #[allow(non_snake_case)] consumedExp: Option<i64>,
-    consumed_money: Option<i64>,
-    #[allow(non_snake_case)] consumedMoney: Option<i64>,
-    consumed_gang: Option<i64>,
-    #[allow(non_snake_case)] consumedGang: Option<i64>,
-    consumed_cultivation_exp: Option<i64>,
-    #[allow(non_snake_case)] consumedCultivationExp: Option<i64>,
     date: String,
+    force: Option<bool>,
 ) -> Result<i64, String> {
     let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
-    let fl = from_level.or(fromLevel);
-    let tl = to_level.or(toLevel);
-    let fe = from_exp.or(fromExp);
-    let te = to_exp.or(toExp);
-    let cexp = consumed_exp.or(consumedExp).unwrap_or(0);
-    let cmoney = consumed_money.or(consumedMoney).unwrap_or(0);
-    let cgang = consumed_gang.or(consumedGang).unwrap_or(0);
-    let ccexp = consumed_cultivation_exp.or(consumedCultivationExp).unwrap_or(0);
-    state.add_change_log(aid, category, name, fl, tl, fe, te, cexp, cmoney, cgang, ccexp, date)
+    let log = state::NewChangeLog {
+        category, name,
+        from_level: from_level.or(fromLevel), to_level: to_level.or(toLevel),
+        from_exp: from_exp.or(fromExp), to_exp: to_exp.or(toExp),
+        date,
+    };
+    let id = blocking(&state, move |db| db.add_change_log(aid, log, force.unwrap_or(false))).await?;
+    data_changed(&app, Entity::ChangeLog, Action::Create, Some(aid), Some(id));
+    Ok(id)
 }
 
 #[tauri::command]
-fn get_change_logs(
-    state: State<DbState>,
+async fn get_change_logs(
+    state: State<'_, DbState>,
     account_id: Option<i64>,
     #[allow(non_snake_case)] accountId: Option<i64>,
 ) -> Result<Vec<state::ChangeLog>, String> {
     let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
-    state.get_change_logs(aid)
+    blocking(&state, move |db| db.get_change_logs(aid)).await
+}
+
+// 返回新写入的撤销记录 id
+#[tauri::command]
+async fn revert_change_log(app: AppHandle, state: State<'_, DbState>, id: i64) -> Result<i64, String> {
+    let r = blocking(&state, move |db| db.revert_change_log(id)).await?;
+    let aid = Some(r.account_id);
+    data_changed(&app, Entity::ChangeLog, Action::Create, aid, Some(r.revert_id));
+    data_changed(&app, events::category_entity(&r.category), Action::Update, aid, Some(r.target_id));
+    data_changed(&app, Entity::Account, Action::Update, aid, aid);
+    Ok(r.revert_id)
+}
+
+#[tauri::command]
+async fn quote_upgrades(state: State<'_, DbState>, account_id: i64, items: Vec<state::UpgradeItem>, date: Option<String>) -> Result<state::UpgradeQuote, String> {
+    blocking(&state, move |db| db.quote_upgrades(account_id, items, date)).await
+}
+
+#[tauri::command]
+async fn apply_upgrades(
+    app: AppHandle,
+    state: State<'_, DbState>,
+    account_id: i64,
+    items: Vec<state::UpgradeItem>,
+    date: Option<String>,
+    force: Option<bool>,
+) -> Result<Vec<i64>, String> {
+    let rows: Vec<(String, i64)> = items.iter().map(|i| (i.category.clone(), i.id)).collect();
+    let ids = blocking(&state, move |db| db.apply_upgrades(account_id, items, date, force.unwrap_or(false))).await?;
+    let aid = Some(account_id);
+    for id in &ids {
+        data_changed(&app, Entity::ChangeLog, Action::Create, aid, Some(*id));
+    }
+    for (category, id) in &rows {
+        data_changed(&app, events::category_entity(category), Action::Update, aid, Some(*id));
+    }
+    data_changed(&app, Entity::Account, Action::Update, aid, aid);
+    Ok(ids)
+}
+
+#[tauri::command]
+async fn recompute_change_log(state: State<'_, DbState>, id: i64) -> Result<state::ChangeLogRecompute, String> {
+    blocking(&state, move |db| db.recompute_change_log(id)).await
+}
+
+// Cost tables
+#[tauri::command]
+async fn list_cost_tables(
+    state: State<'_, DbState>,
+    server_type: Option<String>,
+    #[allow(non_snake_case)] serverType: Option<String>,
+) -> Result<Vec<state::CostTableVersion>, String> {
+    blocking(&state, move |db| db.list_cost_tables(server_type.or(serverType))).await
+}
+
+#[tauri::command]
+async fn get_cost_table(state: State<'_, DbState>, id: i64) -> Result<costs::CostTable, String> {
+    blocking(&state, move |db| db.get_cost_table(id)).await
+}
+
+#[tauri::command]
+async fn import_cost_tables(
+    app: AppHandle,
+    tasks: State<'_, Tasks>,
+    state: State<'_, DbState>,
+    path: String,
+    task_id: Option<String>,
+    #[allow(non_snake_case)] taskId: Option<String>,
+) -> Result<state::CostImportResult, String> {
+    let result = blocking_task(app.clone(), &tasks, &state, task_id.or(taskId), move |db, progress| db.import_cost_tables(path, progress)).await?;
+    if result.version_id.is_some() {
+        data_changed(&app, Entity::CostTable, Action::Create, None, result.version_id);
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+async fn validate_cost_tables(state: State<'_, DbState>, path: Option<String>) -> Result<cost_overrides::CostFileReport, String> {
+    blocking(&state, move |db| db.validate_cost_tables(path)).await
+}
+
+// 累计表/单级表互转并报告不一致之处
+#[tauri::command]
+fn convert_cost_table(input: cost_convert::ConvertInput) -> Result<cost_convert::ConvertReport, String> {
+    cost_convert::convert(&input)
+}
+
+#[tauri::command]
+async fn reset_cost_tables(app: AppHandle, state: State<'_, DbState>) -> Result<usize, String> {
+    let n = blocking(&state, move |db| db.reset_cost_tables()).await?;
+    data_changed(&app, Entity::CostTable, Action::Delete, None, None);
+    Ok(n)
 }
 
 // Master skills
 #[tauri::command]
-fn add_master_skill(state: State<DbState>, account_id: i64, skill_name: String, current_level: i32, target_level: i32) -> Result<i64, String> {
-    state.add_master_skill(account_id, skill_name, current_level, target_level)
+async fn add_master_skill(app: AppHandle, state: State<'_, DbState>, account_id: i64, skill_name: String, current_level: i32, target_level: i32) -> Result<i64, String> {
+    let id = blocking(&state, move |db| db.add_master_skill(account_id, skill_name, current_level, target_level)).await?;
+    data_changed(&app, Entity::MasterSkill, Action::Create, Some(account_id), Some(id));
+    Ok(id)
 }
 
 #[tauri::command]
-fn update_master_skill(state: State<DbState>, id: i64, current_level: i32, target_level: i32) -> Result<(), String> {
-    state.update_master_skill(id, current_level, target_level)
+async fn update_master_skill(app: AppHandle, state: State<'_, DbState>, id: i64, current_level: i32, target_level: i32) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("master_skills", id)?;
+        db.update_master_skill(id, current_level, target_level)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::MasterSkill, Action::Update, aid, Some(id));
+    Ok(())
 }
 
 #[tauri::command]
-fn delete_master_skill(state: State<DbState>, id: i64) -> Result<(), String> {
-    state.delete_master_skill(id)
+async fn delete_master_skill(app: AppHandle, state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("master_skills", id)?;
+        db.delete_master_skill(id)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::MasterSkill, Action::Delete, aid, Some(id));
+    Ok(())
 }
 
 #[tauri::command]
-fn get_master_skills(state: State<DbState>, account_id: i64) -> Result<Vec<state::Skill>, String> {
-    state.get_master_skills(account_id)
+async fn get_master_skills(state: State<'_, DbState>, account_id: i64) -> Result<Vec<state::Skill>, String> {
+    blocking(&state, move |db| db.get_master_skills(account_id)).await
 }
 
 // Assist skills
 #[tauri::command]
-fn add_assist_skill(state: State<DbState>, account_id: i64, skill_name: String, current_level: i32, target_level: i32) -> Result<i64, String> {
-    state.add_assist_skill(account_id, skill_name, current_level, target_level)
+async fn add_assist_skill(app: AppHandle, state: State<'_, DbState>, account_id: i64, skill_name: String, current_level: i32, target_level: i32) -> Result<i64, String> {
+    let id = blocking(&state, move |db| db.add_assist_skill(account_id, skill_name, current_level, target_level)).await?;
+    data_changed(&app, Entity::AssistSkill, Action::Create, Some(account_id), Some(id));
+    Ok(id)
 }
 
 #[tauri::command]
-fn update_assist_skill(state: State<DbState>, id: i64, current_level: i32, target_level: i32) -> Result<(), String> {
-    state.update_assist_skill(id, current_level, target_level)
+async fn update_assist_skill(app: AppHandle, state: State<'_, DbState>, id: i64, current_level: i32, target_level: i32) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("assist_skills", id)?;
+        db.update_assist_skill(id, current_level, target_level)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::AssistSkill, Action::Update, aid, Some(id));
+    Ok(())
 }
 
 #[tauri::command]
-fn delete_assist_skill(state: State<DbState>, id: i64) -> Result<(), String> {
-    state.delete_assist_skill(id)
+async fn delete_assist_skill(app: AppHandle, state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("assist_skills", id)?;
+        db.delete_assist_skill(id)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::AssistSkill, Action::Delete, aid, Some(id));
+    Ok(())
 }
 
 #[tauri::command]
-fn get_assist_skills(state: State<DbState>, account_id: i64) -> Result<Vec<state::Skill>, String> {
-    state.get_assist_skills(account_id)
+async fn get_assist_skills(state: State<'_, DbState>, account_id: i64) -> Result<Vec<state::Skill>, String> {
+    blocking(&state, move |db| db.get_assist_skills(account_id)).await
 }
 
 // Cultivations
 #[tauri::command]
-fn add_cultivation(
-    state: State<DbState>,
+#[allow(clippy::too_many_arguments)]
+async fn add_cultivation(
+    app: AppHandle,
+    state: State<'_, DbState>,
     account_id: Option<i64>,
     #[allow(non_snake_case)] accountId: Option<i64>,
     name: Option<String>,
@@ -173,18 +613,28 @@ fn add_cultivation(
     #[allow(non_snake_case)] currentLevel: Option<i32>,
     target_level: Option<i32>,
     #[allow(non_snake_case)] targetLevel: Option<i32>,
+    pet_id: Option<i64>,
+    #[allow(non_snake_case)] petId: Option<i64>,
 ) -> Result<i64, String> {
     let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
-    let cur_exp = current_exp.or(currentExp).unwrap_or(0);
-    let cur_lvl = current_level.or(currentLevel).unwrap_or(0);
-    let tgt_lvl = target_level.or(targetLevel).unwrap_or(0);
-    let nm = name.unwrap_or_else(|| "".to_string());
-    state.add_cultivation(aid, nm, r#type, mode, cur_exp, cur_lvl, tgt_lvl)
+    let c = state::NewCultivation {
+        name: name.unwrap_or_default(),
+        r#type,
+        mode,
+        current_exp: current_exp.or(currentExp).unwrap_or(0),
+        current_level: current_level.or(currentLevel).unwrap_or(0),
+        target_level: target_level.or(targetLevel).unwrap_or(0),
+    };
+    let id = blocking(&state, move |db| db.add_cultivation(aid, c, pet_id.or(petId))).await?;
+    data_changed(&app, Entity::Cultivation, Action::Create, Some(aid), Some(id));
+    Ok(id)
 }
 
 #[tauri::command]
-fn update_cultivation(
-    state: State<DbState>,
+#[allow(clippy::too_many_arguments)]
+async fn update_cultivation(
+    app: AppHandle,
+    state: State<'_, DbState>,
     id: i64,
     name: Option<String>,
     mode: String,
@@ -198,35 +648,163 @@ fn update_cultivation(
     let cur_exp = current_exp.or(currentExp).unwrap_or(0);
     let cur_lvl = current_level.or(currentLevel).unwrap_or(0);
     let tgt_lvl = target_level.or(targetLevel).unwrap_or(0);
-    state.update_cultivation(id, name, mode, cur_exp, cur_lvl, tgt_lvl)
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("cultivations", id)?;
+        db.update_cultivation(id, name, mode, cur_exp, cur_lvl, tgt_lvl)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::Cultivation, Action::Update, aid, Some(id));
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_cultivation_pet(
+    app: AppHandle,
+    state: State<'_, DbState>,
+    id: i64,
+    pet_id: Option<i64>,
+    #[allow(non_snake_case)] petId: Option<i64>,
+) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("cultivations", id)?;
+        db.set_cultivation_pet(id, pet_id.or(petId))?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::Cultivation, Action::Update, aid, Some(id));
+    Ok(())
 }
 
 #[tauri::command]
-fn delete_cultivation(state: State<DbState>, id: i64) -> Result<(), String> {
-    state.delete_cultivation(id)
+async fn delete_cultivation(app: AppHandle, state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("cultivations", id)?;
+        db.delete_cultivation(id)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::Cultivation, Action::Delete, aid, Some(id));
+    Ok(())
 }
 
 #[tauri::command]
-fn get_cultivations(
-    state: State<DbState>,
+async fn get_cultivations(
+    state: State<'_, DbState>,
     account_id: Option<i64>,
     #[allow(non_snake_case)] accountId: Option<i64>,
+    r#type: Option<String>,
 ) -> Result<Vec<state::Cultivation>, String> {
     let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
-    state.get_cultivations(aid)
+    let mut v = blocking(&state, move |db| db.get_cultivations(aid)).await?;
+    if let Some(t) = r#type { v.retain(|c| c.r#type == t); }
+    Ok(v)
+}
+
+#[tauri::command]
+async fn get_cultivation_summary(
+    state: State<'_, DbState>,
+    account_id: Option<i64>,
+    #[allow(non_snake_case)] accountId: Option<i64>,
+) -> Result<state::CultivationSummary, String> {
+    let aid = account_id.or(accountId).ok_or_else(|| "missing account_id".to_string())?;
+    blocking(&state, move |db| db.get_cultivation_summary(aid)).await
+}
+
+#[tauri::command]
+fn list_cultivation_kinds() -> Vec<catalog::CultivationKind> {
+    catalog::CULTIVATIONS.to_vec()
+}
+
+// Pets
+#[tauri::command]
+async fn add_pet(app: AppHandle, state: State<'_, DbState>, account_id: i64, pet: state::PetInput) -> Result<i64, String> {
+    let id = blocking(&state, move |db| db.add_pet(account_id, pet)).await?;
+    data_changed(&app, Entity::Pet, Action::Create, Some(account_id), Some(id));
+    Ok(id)
+}
+
+#[tauri::command]
+async fn update_pet(app: AppHandle, state: State<'_, DbState>, id: i64, pet: state::PetInput) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("pets", id)?;
+        db.update_pet(id, pet)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::Pet, Action::Update, aid, Some(id));
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_pet(app: AppHandle, state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("pets", id)?;
+        db.delete_pet(id)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::Pet, Action::Delete, aid, Some(id));
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_pets(state: State<'_, DbState>, account_id: i64) -> Result<Vec<state::Pet>, String> {
+    blocking(&state, move |db| db.get_pets(account_id)).await
+}
+
+// Equipment
+#[tauri::command]
+async fn add_equipment(app: AppHandle, state: State<'_, DbState>, account_id: i64, equipment: state::EquipmentInput) -> Result<i64, String> {
+    let id = blocking(&state, move |db| db.add_equipment(account_id, equipment)).await?;
+    data_changed(&app, Entity::Equipment, Action::Create, Some(account_id), Some(id));
+    Ok(id)
+}
+
+#[tauri::command]
+async fn update_equipment(app: AppHandle, state: State<'_, DbState>, id: i64, equipment: state::EquipmentInput) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("equipment", id)?;
+        db.update_equipment(id, equipment)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::Equipment, Action::Update, aid, Some(id));
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_equipment(app: AppHandle, state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let aid = blocking(&state, move |db| {
+        let aid = db.account_of("equipment", id)?;
+        db.delete_equipment(id)?;
+        Ok(aid)
+    }).await?;
+    data_changed(&app, Entity::Equipment, Action::Delete, aid, Some(id));
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_equipment(state: State<'_, DbState>, account_id: i64) -> Result<Vec<state::Equipment>, String> {
+    blocking(&state, move |db| db.get_equipment(account_id)).await
 }
 
 fn main() {
     tauri::Builder::default()
         .manage(DbState::default())
+        .manage(Tasks::default())
         .invoke_handler(tauri::generate_handler![
-            db_init,
+            db_init, repair_dates, cancel_task,
+            list_schools, get_school,
             add_account, update_account, delete_account, get_all_accounts,
+            list_trash, restore_from_trash, purge_from_trash, get_trash_retention_days, set_trash_retention_days,
+            change_school, revert_school_change, get_school_changes,
+            set_account_balances, get_level_progress, get_level_caps, list_rule_profiles, get_overview, get_account_bundle, get_account_remaining_cost, get_all_remaining_costs, plan_upgrades, reach_with_budget, forecast_targets, character_exp_table,
             add_master_skill, update_master_skill, delete_master_skill, get_master_skills,
             add_assist_skill, update_assist_skill, delete_assist_skill, get_assist_skills,
             add_cultivation, update_cultivation, delete_cultivation, get_cultivations,
-            add_spend_log, get_spend_logs, get_spend_summary_daily, get_spend_summary_monthly
-            ,add_change_log, get_change_logs
+            get_cultivation_summary, list_cultivation_kinds, set_cultivation_pet,
+            add_pet, update_pet, delete_pet, get_pets,
+            add_equipment, update_equipment, delete_equipment, get_equipment,
+            add_spend_log, get_spend_logs, get_spend_summary_daily, get_spend_summary_monthly,
+            add_change_log, get_change_logs, recompute_change_log, revert_change_log,
+            quote_upgrades, apply_upgrades,
+            list_cost_tables, get_cost_table, import_cost_tables, validate_cost_tables, reset_cost_tables,
+            convert_cost_table
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,385 @@
+// 预算内的升级规划：给定梦幻币预算（可选经验、帮贡、修炼经验预算），
+// 在师门技能、辅助技能与修炼之间分配，决定各项升到几级；
+// 以及反向计算单个技能/修炼用现有资源最多能升到哪一级。
+// 逐级贪心：每一步选“价值 / 消耗占预算比例”最高且仍在预算内的一级；
+// targets 目标先按完成所需消耗从低到高凑满尽可能多的目标，余下预算再逐级推进。
+
+use std::collections::HashMap;
+
+use crate::costs::CostTable;
+use crate::state::Resources;
+use crate::tasks;
+
+pub const OBJECTIVES: [&str; 3] = ["levels", "weighted", "targets"];
+const STEP_REPORT_INTERVAL: u64 = 50;
+
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlanBudget {
+    pub gold: i64,
+    // 不填表示不限制
+    #[serde(default)]
+    pub exp: Option<i64>,
+    #[serde(default)]
+    pub gang: Option<i64>,
+    #[serde(default)]
+    pub cultivation_exp: Option<i64>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct PlanRequest {
+    pub budget: PlanBudget,
+    // levels：升级总数最多；weighted：按权重加权的等级数最多；targets：达成目标等级的项最多
+    #[serde(default = "default_objective")]
+    pub objective: String,
+    // 权重按技能/修炼名称或类别（master / assist / cultivation）查找，名称优先，默认 1，0 表示不参与
+    #[serde(default)]
+    pub weights: HashMap<String, f64>,
+    // levels / weighted 下是否只升到各项的目标等级
+    #[serde(default)]
+    pub within_targets: bool,
+}
+
+fn default_objective() -> String { "levels".to_string() }
+
+// 可升级的一项；max_level 为人物等级与消耗表共同限制的上限
+#[derive(Clone)]
+pub struct Candidate {
+    pub category: &'static str,
+    pub id: i64,
+    pub name: String,
+    pub r#type: String,
+    pub mode: String,
+    pub current_level: i32,
+    pub current_exp: i64,
+    pub target_level: i32,
+    pub max_level: i32,
+}
+
+#[derive(serde::Serialize)]
+pub struct PlanItem {
+    pub category: String,
+    pub id: i64,
+    pub name: String,
+    pub from_level: i32,
+    pub to_level: i32,
+    pub target_level: i32,
+    pub reached_target: bool,
+    pub cost: Resources,
+}
+
+#[derive(serde::Serialize)]
+pub struct UpgradePlan {
+    pub objective: String,
+    pub budget: PlanBudget,
+    pub items: Vec<PlanItem>,
+    pub total: Resources,
+    pub remaining: PlanBudget,
+    pub levels_gained: i32,
+    pub targets_reached: usize,
+    pub score: f64,
+}
+
+// 单项反算时的可用资源，不填表示不限制（至少给出一项）
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct Available {
+    #[serde(default)]
+    pub gold: Option<i64>,
+    #[serde(default)]
+    pub exp: Option<i64>,
+    #[serde(default)]
+    pub gang: Option<i64>,
+    #[serde(default)]
+    pub cultivation_exp: Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+pub struct Reach {
+    pub category: String,
+    pub id: i64,
+    pub name: String,
+    pub from_level: i32,
+    pub from_exp: i64,
+    // 能达到的最高等级；修炼另给出该级内已积累的经验
+    pub level: i32,
+    pub exp: i64,
+    pub max_level: i32,
+    // budget：资源不够再升一级；max_level：已到上限
+    pub limited_by: String,
+    pub spent: Resources,
+    pub leftover: Available,
+}
+
+// 一项预算上限及其对应的资源列
+type Limit = (Option<i64>, fn(&Resources) -> i64);
+
+pub fn plus(a: &Resources, b: &Resources) -> Resources {
+    Resources { exp: a.exp + b.exp, gold: a.gold + b.gold, gang: a.gang + b.gang, cultivation_exp: a.cultivation_exp + b.cultivation_exp }
+}
+
+impl PlanBudget {
+    fn limits(&self) -> [Limit; 4] {
+        [(Some(self.gold), |r| r.gold), (self.exp, |r| r.exp), (self.gang, |r| r.gang), (self.cultivation_exp, |r| r.cultivation_exp)]
+    }
+
+    fn allows(&self, spent: &Resources) -> bool {
+        self.limits().iter().all(|(limit, get)| limit.is_none_or(|l| get(spent) <= l))
+    }
+
+    // 消耗占各项预算比例之和，用于比较不同资源构成的消耗
+    fn share(&self, cost: &Resources) -> f64 {
+        self.limits().iter().map(|(limit, get)| match limit {
+            Some(l) if *l > 0 => get(cost) as f64 / *l as f64,
+            _ => 0.0,
+        }).sum()
+    }
+
+    fn minus(&self, spent: &Resources) -> PlanBudget {
+        PlanBudget {
+            gold: self.gold - spent.gold,
+            exp: self.exp.map(|v| v - spent.exp),
+            gang: self.gang.map(|v| v - spent.gang),
+            cultivation_exp: self.cultivation_exp.map(|v| v - spent.cultivation_exp),
+        }
+    }
+}
+
+impl Available {
+    fn limits(&self) -> [Limit; 4] {
+        [(self.gold, |r| r.gold), (self.exp, |r| r.exp), (self.gang, |r| r.gang), (self.cultivation_exp, |r| r.cultivation_exp)]
+    }
+
+    fn allows(&self, spent: &Resources) -> bool {
+        self.limits().iter().all(|(limit, get)| limit.is_none_or(|l| get(spent) <= l))
+    }
+
+    fn minus(&self, spent: &Resources) -> Available {
+        Available {
+            gold: self.gold.map(|v| v - spent.gold),
+            exp: self.exp.map(|v| v - spent.exp),
+            gang: self.gang.map(|v| v - spent.gang),
+            cultivation_exp: self.cultivation_exp.map(|v| v - spent.cultivation_exp),
+        }
+    }
+}
+
+/// 从 level 级（已积累 exp）升一级的消耗，资源口径与变更记录一致
+fn step_cost(costs: &CostTable, c: &Candidate, level: i32, exp: i64) -> Resources {
+    match c.category {
+        "master" => {
+            let s = costs.master_cost(level, level + 1);
+            Resources { exp: s.exp, gold: s.money, ..Default::default() }
+        }
+        "assist" => {
+            let s = costs.assist_cost(level, level + 1);
+            Resources { exp: s.exp, gold: s.money, gang: s.gang, ..Default::default() }
+        }
+        _ => {
+            let s = costs.cultivation_cost(&c.r#type, &c.mode, level, exp, level + 1);
+            Resources { gold: s.money, cultivation_exp: s.exp, ..Default::default() }
+        }
+    }
+}
+
+/// 从当前等级到目标等级还需的全部消耗
+pub fn remaining_cost(costs: &CostTable, c: &Candidate) -> Resources {
+    let mut cost = Resources::default();
+    for level in c.current_level..c.target_level {
+        let exp = if level == c.current_level { c.current_exp } else { 0 };
+        cost = plus(&cost, &step_cost(costs, c, level, exp));
+    }
+    cost
+}
+
+fn weight_of(weights: &HashMap<String, f64>, c: &Candidate) -> f64 {
+    weights.get(&c.name).or_else(|| weights.get(c.category)).copied().unwrap_or(1.0)
+}
+
+struct Progress {
+    level: i32,
+    exp: i64,
+    cost: Resources,
+}
+
+// 逐级贪心的一步：仍放得进预算的各项下一级中，价值 / 消耗占预算比例最高的一项
+fn best_step(costs: &CostTable, cands: &[Candidate], limits: &[i32], weights: &[f64], budget: &PlanBudget, progress: &[Progress], spent: &Resources) -> Option<(usize, Resources)> {
+    let mut best: Option<(usize, f64, Resources)> = None;
+    for (i, c) in cands.iter().enumerate() {
+        let p = &progress[i];
+        if weights[i] <= 0.0 || p.level >= limits[i] { continue; }
+        let cost = step_cost(costs, c, p.level, p.exp);
+        if !budget.allows(&plus(spent, &cost)) { continue; }
+        let ratio = weights[i] / budget.share(&cost).max(1e-12);
+        if best.as_ref().is_none_or(|b| ratio > b.1) { best = Some((i, ratio, cost)); }
+    }
+    best.map(|(i, _, cost)| (i, cost))
+}
+
+/// 每推进 STEP_REPORT_INTERVAL 级检查一次取消并报告进度，total 为各项可升级数之和（上界）
+pub fn plan(costs: &CostTable, cands: Vec<Candidate>, req: &PlanRequest, task: &tasks::Progress) -> Result<UpgradePlan, String> {
+    let budget = req.budget;
+    let weights: Vec<f64> = cands.iter().map(|c| if req.objective == "levels" { 1.0 } else { weight_of(&req.weights, c) }).collect();
+    let targets: Vec<i32> = cands.iter().map(|c| c.target_level.min(c.max_level)).collect();
+    let mut progress: Vec<Progress> = cands.iter().map(|c| Progress { level: c.current_level, exp: c.current_exp, cost: Resources::default() }).collect();
+    let mut spent = Resources::default();
+
+    if req.objective == "targets" {
+        // 先整项完成：按完成所需消耗占预算比例从低到高，放得下就完成
+        let mut order: Vec<(usize, Resources)> = cands.iter().enumerate()
+            .filter(|(i, c)| weights[*i] > 0.0 && targets[*i] > c.current_level)
+            .map(|(i, c)| (i, remaining_cost(costs, &Candidate { target_level: targets[i], ..c.clone() })))
+            .collect();
+        order.sort_by(|a, b| budget.share(&a.1).total_cmp(&budget.share(&b.1)).then(weights[b.0].total_cmp(&weights[a.0])));
+        for (i, cost) in order {
+            if !budget.allows(&plus(&spent, &cost)) { continue; }
+            progress[i] = Progress { level: targets[i], exp: 0, cost };
+            spent = plus(&spent, &cost);
+        }
+    }
+    let limits: Vec<i32> = cands.iter().enumerate()
+        .map(|(i, c)| if req.within_targets || req.objective == "targets" { targets[i] } else { c.max_level })
+        .collect();
+    // 逐级贪心，直到没有任何一级还能放进预算
+    let total: u64 = cands.iter().zip(&limits).map(|(c, &l)| (l - c.current_level).max(0) as u64).sum();
+    let mut steps = 0u64;
+    while let Some((i, cost)) = best_step(costs, &cands, &limits, &weights, &budget, &progress, &spent) {
+        if steps.is_multiple_of(STEP_REPORT_INTERVAL) { task.step("plan", steps, total)?; }
+        steps += 1;
+        let p = &mut progress[i];
+        p.level += 1;
+        p.exp = 0;
+        p.cost = plus(&p.cost, &cost);
+        spent = plus(&spent, &cost);
+    }
+    task.report("plan", total, total);
+
+    let mut items = Vec::new();
+    let (mut levels_gained, mut targets_reached, mut weighted) = (0, 0, 0.0);
+    for ((c, p), w) in cands.into_iter().zip(progress).zip(weights) {
+        if p.level <= c.current_level { continue; }
+        let reached_target = c.target_level > c.current_level && p.level >= c.target_level;
+        levels_gained += p.level - c.current_level;
+        weighted += w * (p.level - c.current_level) as f64;
+        if reached_target { targets_reached += 1; }
+        items.push(PlanItem {
+            category: c.category.to_string(), id: c.id, name: c.name,
+            from_level: c.current_level, to_level: p.level, target_level: c.target_level,
+            reached_target, cost: p.cost,
+        });
+    }
+    let score = match req.objective.as_str() {
+        "weighted" => weighted,
+        "targets" => targets_reached as f64,
+        _ => levels_gained as f64,
+    };
+    Ok(UpgradePlan { objective: req.objective.clone(), budget, items, total: spent, remaining: budget.minus(&spent), levels_gained, targets_reached, score })
+}
+
+/// 逐级累加直到资源不够或到达上限；修炼再用剩余资源积累下一级的经验（不足以升级）
+pub fn reach(costs: &CostTable, c: &Candidate, available: &Available) -> Reach {
+    let mut spent = Resources::default();
+    let (mut level, mut exp) = (c.current_level, c.current_exp);
+    while level < c.max_level {
+        let cost = step_cost(costs, c, level, exp);
+        if !available.allows(&plus(&spent, &cost)) { break; }
+        spent = plus(&spent, &cost);
+        level += 1;
+        exp = 0;
+    }
+    if c.category == "cultivation" && level < c.max_level {
+        let unit = costs.cultivation_unit_price(&c.mode).unwrap_or(0);
+        let mut add = costs.cultivation_level_exp(&c.r#type, level + 1) - exp - 1;
+        // 每 10 点经验按一次收费
+        if let (Some(gold), true) = (available.gold, unit > 0) { add = add.min((gold - spent.gold) / unit * 10); }
+        if let Some(cexp) = available.cultivation_exp { add = add.min(cexp - spent.cultivation_exp); }
+        if add > 0 {
+            exp += add;
+            spent.gold += (add + 9) / 10 * unit;
+            spent.cultivation_exp += add;
+        }
+    }
+    Reach {
+        category: c.category.to_string(), id: c.id, name: c.name.clone(),
+        from_level: c.current_level, from_exp: c.current_exp, level, exp, max_level: c.max_level,
+        limited_by: if level >= c.max_level { "max_level" } else { "budget" }.to_string(),
+        spent, leftover: available.minus(&spent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    fn costs() -> CostTable {
+        rules::profile_of(rules::DEFAULT_SERVER_TYPE).builtin_cost_table()
+    }
+
+    fn skill(id: i64, category: &'static str, current_level: i32, target_level: i32) -> Candidate {
+        Candidate {
+            category, id, name: format!("技能{}", id), r#type: String::new(), mode: String::new(),
+            current_level, current_exp: 0, target_level, max_level: 150,
+        }
+    }
+
+    fn request(gold: i64, objective: &str, within_targets: bool) -> PlanRequest {
+        PlanRequest { budget: PlanBudget { gold, ..Default::default() }, objective: objective.to_string(), weights: HashMap::new(), within_targets }
+    }
+
+    fn cost_between(costs: &CostTable, c: &Candidate, to_level: i32) -> Resources {
+        remaining_cost(costs, &Candidate { target_level: to_level, ..c.clone() })
+    }
+
+    #[test]
+    fn plan_stays_within_budget_and_item_costs_add_up() {
+        let costs = costs();
+        let cands = vec![skill(1, "master", 60, 100), skill(2, "assist", 20, 80)];
+        let plan = plan(&costs, cands.clone(), &request(2_000_000, "levels", false), &tasks::Progress::none()).unwrap();
+        assert!(plan.total.gold <= 2_000_000 && plan.levels_gained > 0);
+        assert_eq!(plan.remaining.gold, 2_000_000 - plan.total.gold);
+        let mut sum = Resources::default();
+        for item in &plan.items {
+            let c = cands.iter().find(|c| c.id == item.id).unwrap();
+            let expected = cost_between(&costs, c, item.to_level);
+            assert_eq!((item.cost.gold, item.cost.exp, item.cost.gang), (expected.gold, expected.exp, expected.gang));
+            sum = plus(&sum, &item.cost);
+        }
+        assert_eq!((sum.gold, sum.exp), (plan.total.gold, plan.total.exp));
+        // 再升任何一级都会超出预算
+        for c in &cands {
+            let to = plan.items.iter().find(|i| i.id == c.id).map_or(c.current_level, |i| i.to_level);
+            assert!(plan.total.gold + step_cost(&costs, c, to, 0).gold > 2_000_000);
+        }
+    }
+
+    #[test]
+    fn within_targets_and_targets_objective_stop_at_targets() {
+        let costs = costs();
+        let cands = vec![skill(1, "master", 60, 62), skill(2, "assist", 20, 25)];
+        let rich = plan(&costs, cands.clone(), &request(i64::MAX / 4, "levels", true), &tasks::Progress::none()).unwrap();
+        assert!(rich.items.iter().all(|i| i.to_level == i.target_level && i.reached_target));
+        assert_eq!(rich.targets_reached, 2);
+
+        // 预算只够完成较便宜的一项时，targets 先完成它
+        let cheap = cands.iter().map(|c| cost_between(&costs, c, c.target_level).gold).min().unwrap();
+        let plan = plan(&costs, cands, &request(cheap, "targets", false), &tasks::Progress::none()).unwrap();
+        assert_eq!(plan.targets_reached, 1);
+        assert_eq!(plan.score, 1.0);
+    }
+
+    #[test]
+    fn zero_weight_items_are_left_alone() {
+        let mut req = request(5_000_000, "weighted", false);
+        req.weights.insert("assist".to_string(), 0.0);
+        let plan = plan(&costs(), vec![skill(1, "master", 60, 100), skill(2, "assist", 20, 80)], &req, &tasks::Progress::none()).unwrap();
+        assert!(plan.items.iter().all(|i| i.category == "master"));
+    }
+
+    #[test]
+    fn cancelled_plan_returns_an_error() {
+        let task = tasks::Progress::new(|_, _, _| {}, Arc::new(AtomicBool::new(true)));
+        let err = plan(&costs(), vec![skill(1, "master", 60, 100)], &request(5_000_000, "levels", false), &task).err();
+        assert_eq!(err.as_deref(), Some(tasks::CANCELLED));
+    }
+}
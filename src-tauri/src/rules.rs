@@ -0,0 +1,111 @@
+// 服务器规则档案：不同服务器类型的消耗表、等级上限与修炼收费档位不同，
+// 账号按 server_type 选择档案，所有消耗计算与校验都经由档案进行。
+// 档案中的消耗表是内置版本，启动时写入 cost_tables；补丁改表时同时更新
+// cost_version 与 cost_effective_date，旧版本保留在库中供历史记录对照。
+// 目前只有普通服档案；其他服务器的上限与修炼价格核实后再在这里添加。
+
+use crate::costs::{self, CostTable, CultivationPrice, SkillLevelCost};
+
+pub const DEFAULT_SERVER_TYPE: &str = "normal";
+
+#[derive(serde::Serialize)]
+pub struct RuleProfile {
+    pub server_type: &'static str,
+    pub label: &'static str,
+    pub max_skill_level: i32,
+    // 技能上限 = 人物等级 + skill_cap_offset
+    pub skill_cap_offset: i32,
+    // 修炼上限 = (人物等级 - 20) / 5，且不超过 max_cultivation_level
+    pub max_cultivation_level: i32,
+    pub cost_version: &'static str,
+    pub cost_effective_date: &'static str,
+    #[serde(skip)]
+    pub cultivation_prices: &'static [(&'static str, i64)],
+    #[serde(skip)]
+    pub master_cost: &'static [(i64, i64)],
+    #[serde(skip)]
+    pub assist_cost: &'static [(i64, i64, i64)],
+    #[serde(skip)]
+    pub person_cultivation_exp: &'static [i64],
+    #[serde(skip)]
+    pub pet_cultivation_exp: &'static [i64],
+    #[serde(skip)]
+    pub character_exp: &'static [i64],
+}
+
+pub static PROFILES: [RuleProfile; 1] = [
+    RuleProfile {
+        server_type: "normal",
+        label: "普通服",
+        max_skill_level: 180,
+        skill_cap_offset: 10,
+        max_cultivation_level: 25,
+        cost_version: "normal-1",
+        cost_effective_date: "2000-01-01",
+        cultivation_prices: &[("2w", 20000), ("3w", 30000)],
+        master_cost: &costs::MASTER_SKILL_COST,
+        assist_cost: &costs::ASSIST_SKILL_COST,
+        person_cultivation_exp: &costs::PERSON_CULTIVATION_EXP,
+        pet_cultivation_exp: costs::PET_CULTIVATION_EXP,
+        character_exp: &costs::CHARACTER_EXP,
+    },
+];
+
+pub fn find_profile(server_type: &str) -> Option<&'static RuleProfile> {
+    PROFILES.iter().find(|p| p.server_type == server_type)
+}
+
+/// 未知的服务器类型按普通服处理（兼容旧数据）
+pub fn profile_of(server_type: &str) -> &'static RuleProfile {
+    find_profile(server_type).unwrap_or(&PROFILES[0])
+}
+
+#[derive(Clone, Copy, serde::Serialize)]
+pub struct LevelCaps {
+    pub server_type: &'static str,
+    pub character_level: i32,
+    pub master_skill: i32,
+    pub assist_skill: i32,
+    pub person_cultivation: i32,
+    pub pet_cultivation: i32,
+}
+
+impl LevelCaps {
+    pub fn cultivation(&self, r#type: &str) -> i32 {
+        if r#type == "pet" { self.pet_cultivation } else { self.person_cultivation }
+    }
+}
+
+impl RuleProfile {
+    pub fn skill_cap(&self, character_level: i32) -> i32 {
+        (character_level.max(0) + self.skill_cap_offset).min(self.max_skill_level)
+    }
+
+    pub fn cultivation_cap(&self, character_level: i32) -> i32 {
+        ((character_level - 20).max(0) / 5).min(self.max_cultivation_level)
+    }
+
+    pub fn level_caps(&self, character_level: i32) -> LevelCaps {
+        LevelCaps {
+            server_type: self.server_type,
+            character_level,
+            master_skill: self.skill_cap(character_level),
+            assist_skill: self.skill_cap(character_level),
+            person_cultivation: self.cultivation_cap(character_level),
+            // 宝宝修炼上限没有单独的游戏数据，共用人物修炼的公式
+            pet_cultivation: self.cultivation_cap(character_level),
+        }
+    }
+
+    /// 档案内置的消耗表
+    pub fn builtin_cost_table(&self) -> CostTable {
+        CostTable {
+            master: self.master_cost.iter().map(|&(exp, money)| SkillLevelCost { exp, money, gang: 0 }).collect(),
+            assist: self.assist_cost.iter().map(|&(exp, money, gang)| SkillLevelCost { exp, money, gang }).collect(),
+            person_cultivation_exp: self.person_cultivation_exp.to_vec(),
+            pet_cultivation_exp: self.pet_cultivation_exp.to_vec(),
+            cultivation_prices: self.cultivation_prices.iter().map(|&(mode, price)| CultivationPrice { mode: mode.to_string(), price }).collect(),
+            character_exp: self.character_exp.to_vec(),
+        }
+    }
+}
@@ -1,18 +1,44 @@
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use std::path::PathBuf;
 use std::fs;
+use crate::catalog;
+use crate::cost_overrides::{self, CostFileReport, CostTableFile};
+use crate::costs::CostTable;
+use crate::dates;
+use crate::forecast::{self, AccountForecast};
+use crate::levels;
+use crate::planner::{self, Available, Candidate, PlanRequest, Reach, UpgradePlan};
+use crate::rules;
+use crate::tasks::Progress;
+use crate::validation;
 
 #[derive(Clone)]
 pub struct DbState {
     conn: std::sync::Arc<std::sync::Mutex<Option<Connection>>>,
+    // 数据库文件；为空时按 db_path_at_project_root 的约定
+    path: Option<PathBuf>,
+    // 初始化时不影响启动的问题（如自定义消耗表被忽略），由 db_init 返回给界面
+    warnings: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
 }
 
 impl Default for DbState {
     fn default() -> Self {
-        Self { conn: std::sync::Arc::new(std::sync::Mutex::new(None)) }
+        Self { conn: std::sync::Arc::new(std::sync::Mutex::new(None)), path: None, warnings: std::sync::Arc::default() }
     }
 }
 
+#[derive(serde::Serialize)]
+pub struct TrashItem {
+    pub category: String, // account/master/assist/cultivation
+    pub id: i64,
+    pub account_id: i64,
+    pub account_name: String,
+    pub name: String,
+    pub deleted_at: String,
+    // 到期自动清理的时间；未开启自动清理时为空
+    pub purge_at: Option<String>,
+}
+
 #[derive(serde::Serialize)]
 pub struct Account {
     pub id: i64,
@@ -21,6 +47,114 @@ pub struct Account {
     pub level: i32,
     pub experience: i64,
     pub gold: i64,
+    pub gang: i64,
+    pub cultivation_exp: i64,
+    pub server_type: String,
+}
+
+// 账号可消耗的资源：经验、梦幻币、帮贡、修炼经验
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct Resources {
+    #[serde(default)]
+    pub exp: i64,
+    #[serde(default)]
+    pub gold: i64,
+    #[serde(default)]
+    pub gang: i64,
+    #[serde(default)]
+    pub cultivation_exp: i64,
+}
+
+// 到目标等级还需的消耗：经验、梦幻币、帮贡、修炼经验
+#[derive(Default, serde::Serialize)]
+pub struct RemainingCost {
+    pub account_id: i64,
+    pub master: Resources,
+    pub assist: Resources,
+    pub person_cultivation: Resources,
+    pub pet_cultivation: Resources,
+    pub total: Resources,
+}
+
+#[derive(serde::Serialize)]
+pub struct AccountOverview {
+    #[serde(flatten)]
+    pub account: Account,
+    pub remaining: RemainingCost,
+    // 目标完成度，0-100
+    pub completion: f64,
+    pub spend_7d: i64,
+    pub spend_30d: i64,
+    pub last_change_date: Option<String>,
+}
+
+// 账号详情：各行附带到目标等级的剩余消耗，cost_version_id 为计算所用消耗表版本
+#[derive(serde::Serialize)]
+pub struct AccountBundle {
+    pub account: Account,
+    pub caps: rules::LevelCaps,
+    pub cost_version_id: Option<i64>,
+    pub master_skills: Vec<SkillWithCost>,
+    pub assist_skills: Vec<SkillWithCost>,
+    pub cultivations: Vec<CultivationWithCost>,
+    pub change_logs: Vec<ChangeLog>,
+    pub remaining: RemainingCost,
+}
+
+#[derive(Default, serde::Serialize)]
+pub struct FleetRemainingCost {
+    pub accounts: Vec<RemainingCost>,
+    pub total: Resources,
+}
+
+// 新建账号的字段；gold、master_skills、cultivations 可省略
+#[derive(serde::Deserialize)]
+pub struct NewAccount {
+    pub name: String,
+    pub school: String,
+    // 默认普通服
+    #[serde(default)]
+    pub server_type: Option<String>,
+    pub level: i32,
+    pub experience: i64,
+    #[serde(default)]
+    pub gold: i64,
+    // 传入时在同一事务中写入该门派全部师门技能
+    pub master_skills: Option<MasterSkillDefaults>,
+    #[serde(default)]
+    pub cultivations: Vec<NewCultivation>,
+}
+
+// 创建账号时一并写入的师门技能默认等级
+#[derive(serde::Deserialize)]
+pub struct MasterSkillDefaults {
+    #[serde(default)]
+    pub current_level: i32,
+    #[serde(default)]
+    pub target_level: i32,
+}
+
+// 新增修炼的字段，创建账号时也用于一并写入的修炼行
+#[derive(serde::Deserialize)]
+pub struct NewCultivation {
+    pub name: String,
+    pub r#type: String,
+    pub mode: String,
+    #[serde(default)]
+    pub current_exp: i32,
+    #[serde(default)]
+    pub current_level: i32,
+    #[serde(default)]
+    pub target_level: i32,
+}
+
+impl NewCultivation {
+    fn fields(&self) -> validation::CultivationFields<'_> {
+        validation::CultivationFields {
+            name: Some(&self.name), r#type: &self.r#type, mode: &self.mode,
+            current_exp: self.current_exp, current_level: self.current_level, target_level: self.target_level,
+        }
+    }
 }
 
 #[derive(serde::Serialize)]
@@ -42,6 +176,38 @@ pub struct Cultivation {
     pub current_exp: i32,
     pub current_level: i32,
     pub target_level: i32,
+    pub pet_id: Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+pub struct CultivationWithCost {
+    #[serde(flatten)]
+    pub cultivation: Cultivation,
+    pub remaining_exp: i64,
+    pub remaining_money: i64,
+}
+
+#[derive(serde::Serialize)]
+pub struct SkillWithCost {
+    #[serde(flatten)]
+    pub skill: Skill,
+    pub remaining_exp: i64,
+    pub remaining_money: i64,
+    pub remaining_gang: i64,
+}
+
+#[derive(Default, serde::Serialize)]
+pub struct CultivationGroup {
+    pub rows: Vec<CultivationWithCost>,
+    pub total_level: i64,
+    pub remaining_exp: i64,
+    pub remaining_money: i64,
+}
+
+#[derive(serde::Serialize)]
+pub struct CultivationSummary {
+    pub person: CultivationGroup,
+    pub pet: CultivationGroup,
 }
 
 #[derive(serde::Serialize)]
@@ -51,6 +217,22 @@ pub struct SpendLog {
     pub amount: i64,
     pub date: String,
     pub note: Option<String>,
+    pub pet_id: Option<i64>,
+    pub equipment_id: Option<i64>,
+    pub change_log_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct Pet {
+    pub id: i64,
+    pub account_id: i64,
+    pub name: String,
+    pub species: String,
+    pub level: i32,
+    pub loyalty: i32,
+    pub skills: Vec<String>,
+    pub notes: Option<String>,
     pub created_at: String,
 }
 
@@ -60,6 +242,17 @@ pub struct SpendSummary {
     pub total: i64,
 }
 
+// 新增变更记录的字段；消耗由后端按记录日期生效的消耗表计算
+pub struct NewChangeLog {
+    pub category: String,
+    pub name: String,
+    pub from_level: Option<i32>,
+    pub to_level: Option<i32>,
+    pub from_exp: Option<i32>,
+    pub to_exp: Option<i32>,
+    pub date: String,
+}
+
 #[derive(serde::Serialize)]
 pub struct ChangeLog {
     pub id: i64,
@@ -76,6 +269,637 @@ pub struct ChangeLog {
     pub consumed_cultivation_exp: i64,
     pub date: String,
     pub created_at: String,
+    // 记录时生效的消耗表版本（cost_tables.id），旧记录为空
+    pub cost_version_id: Option<i64>,
+    // 撤销记录所撤销的变更记录
+    pub reverts_id: Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+pub struct CostTableVersion {
+    pub id: i64,
+    pub server_type: String,
+    pub version: String,
+    pub effective_date: String,
+    // builtin 为档案内置，custom 为用户文件导入
+    pub source: String,
+    pub active: bool,
+    pub created_at: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct CostImportResult {
+    pub report: CostFileReport,
+    pub version_id: Option<i64>,
+    pub file: Option<String>,
+}
+
+// 按今天生效的消耗表重新计算某条变更记录，与当时记录的消耗对照
+#[derive(serde::Serialize)]
+pub struct ChangeLogRecompute {
+    pub change_log_id: i64,
+    pub recorded_version_id: Option<i64>,
+    pub recorded: Resources,
+    pub current_version_id: Option<i64>,
+    pub current: Resources,
+    pub diff: Resources,
+}
+
+// 撤销结果：新写入的撤销记录，以及恢复等级的技能/修炼行
+pub struct ChangeLogRevert {
+    pub revert_id: i64,
+    pub account_id: i64,
+    pub category: String,
+    pub target_id: i64,
+}
+
+// 一项升级：category 为 master/assist/cultivation，id 为对应的技能/修炼行；
+// 修炼的 to_exp 为升级后的当前经验，省略为 0
+#[derive(serde::Deserialize)]
+pub struct UpgradeItem {
+    pub category: String,
+    pub id: i64,
+    pub to_level: i32,
+    #[serde(default)]
+    pub to_exp: i32,
+}
+
+#[derive(serde::Serialize)]
+pub struct UpgradeCost {
+    pub category: String,
+    pub id: i64,
+    pub name: String,
+    pub cost: Resources,
+}
+
+// 按记录日期生效的消耗表计算的一组升级消耗，apply_upgrades 按同样的结果写入
+#[derive(serde::Serialize)]
+pub struct UpgradeQuote {
+    pub cost_version_id: Option<i64>,
+    pub items: Vec<UpgradeCost>,
+    pub total: Resources,
+}
+
+// 已校验的一项升级：要更新的行、新的目标等级与要写入的变更记录
+struct PreparedUpgrade {
+    table: &'static str,
+    id: i64,
+    target_level: i32,
+    log: NewChangeLog,
+    cost: Resources,
+}
+
+// 账号当前适用的规则：档案、等级上限与今天生效的消耗表
+struct AccountRules {
+    profile: &'static rules::RuleProfile,
+    caps: rules::LevelCaps,
+    costs: CostTable,
+}
+
+#[derive(serde::Serialize)]
+pub struct Equipment {
+    pub id: i64,
+    pub account_id: i64,
+    pub slot: String,
+    pub name: String,
+    pub level: i32,
+    pub main_stats: String,
+    pub special: String,
+    pub gem_level: i32,
+    pub gold_spent: i64,
+    pub upgrade_spent: i64, // 关联到该装备的消耗记录合计
+    pub notes: Option<String>,
+    pub created_at: String,
+}
+
+// 新增/修改召唤兽的字段
+#[derive(serde::Deserialize)]
+pub struct PetInput {
+    pub name: String,
+    #[serde(default)]
+    pub species: String,
+    #[serde(default)]
+    pub level: i32,
+    #[serde(default = "default_loyalty")]
+    pub loyalty: i32,
+    #[serde(default)]
+    pub skills: Vec<String>,
+    pub notes: Option<String>,
+}
+
+fn default_loyalty() -> i32 { 100 }
+
+// 新增/修改装备的字段
+#[derive(serde::Deserialize)]
+pub struct EquipmentInput {
+    pub slot: String,
+    pub name: String,
+    #[serde(default)]
+    pub level: i32,
+    #[serde(default)]
+    pub main_stats: String,
+    #[serde(default)]
+    pub special: String,
+    #[serde(default)]
+    pub gem_level: i32,
+    #[serde(default)]
+    pub gold_spent: i64,
+    pub notes: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SchoolChange {
+    pub id: i64,
+    pub account_id: i64,
+    pub from_school: String,
+    pub to_school: String,
+    pub strategy: String,
+    pub date: String,
+    pub reverted: bool,
+    pub created_at: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct DateFix {
+    pub table: String,
+    pub id: i64,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct DateRepairReport {
+    pub repaired: Vec<DateFix>,
+    pub invalid: Vec<DateFix>,
+}
+
+const REPAIR_REPORT_INTERVAL: u64 = 200;
+
+// 修复 spend_logs / change_logs 中格式不规范的日期：能识别的改写为 YYYY-MM-DD，
+// 无法识别的保持原样并在报告中列出（to 为空）。每张表每 REPAIR_REPORT_INTERVAL 行一个检查点。
+fn repair_dates_in(conn: &Connection, progress: &Progress) -> Result<DateRepairReport, String> {
+    let mut report = DateRepairReport { repaired: Vec::new(), invalid: Vec::new() };
+    for table in ["spend_logs", "change_logs"] {
+        let rows: Vec<(i64, String)> = {
+            let mut stmt = conn.prepare(&format!("SELECT id, date FROM {} ORDER BY id", table)).map_err(|e| e.to_string())?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(|e| e.to_string())?;
+            let mut v = Vec::new(); for r in rows { v.push(r.map_err(|e| e.to_string())?); } v
+        };
+        let total = rows.len() as u64;
+        for (i, (id, date)) in rows.into_iter().enumerate() {
+            if (i as u64).is_multiple_of(REPAIR_REPORT_INTERVAL) { progress.step(table, i as u64, total)?; }
+            match dates::normalize_date(&date) {
+                Ok(norm) if norm == date => {}
+                Ok(norm) => {
+                    conn.execute(&format!("UPDATE {} SET date = ?1 WHERE id = ?2", table), params![norm, id]).map_err(|e| e.to_string())?;
+                    report.repaired.push(DateFix { table: table.to_string(), id, from: date, to: norm });
+                }
+                Err(_) => report.invalid.push(DateFix { table: table.to_string(), id, from: date, to: String::new() }),
+            }
+        }
+        progress.report(table, total, total);
+    }
+    Ok(report)
+}
+
+fn account_balances(conn: &Connection, account_id: i64) -> Result<Resources, String> {
+    conn.query_row(
+        "SELECT experience, gold, gang, cultivation_exp FROM accounts WHERE id = ?1 AND deleted_at IS NULL", params![account_id],
+        |r| Ok(Resources { exp: r.get(0)?, gold: r.get(1)?, gang: r.get(2)?, cultivation_exp: r.get(3)? })
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("账号不存在: {}", account_id),
+        e => e.to_string(),
+    })
+}
+
+// 从账号余额中扣除资源（负数即返还）；未 force 时任一余额会因本次扣除变为负数则拒绝
+fn debit_account(conn: &Connection, account_id: i64, cost: &Resources, force: bool) -> Result<(), String> {
+    let bal = account_balances(conn, account_id)?;
+    if !force {
+        let mut short = Vec::new();
+        for (field, need, have) in [
+            ("experience", cost.exp, bal.exp),
+            ("gold", cost.gold, bal.gold),
+            ("gang", cost.gang, bal.gang),
+            ("cultivation_exp", cost.cultivation_exp, bal.cultivation_exp),
+        ] {
+            if need > 0 && have < need { short.push(format!("{} 需要 {}，当前 {}", field, need, have)); }
+        }
+        if !short.is_empty() { return Err(format!("余额不足: {}", short.join("; "))); }
+    }
+    conn.execute(
+        "UPDATE accounts SET experience = experience - ?1, gold = gold - ?2, gang = gang - ?3, cultivation_exp = cultivation_exp - ?4 WHERE id = ?5",
+        params![cost.exp, cost.gold, cost.gang, cost.cultivation_exp, account_id]
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn today(conn: &Connection) -> Result<String, String> {
+    conn.query_row("SELECT date('now','localtime')", [], |r| r.get(0)).map_err(|e| e.to_string())
+}
+
+// 回收站的类别与对应表；账号放在最前，清理时倒序先清技能与修炼
+const TRASH_TABLES: [(&str, &str); 4] = [
+    ("account", "accounts"),
+    ("master", "master_skills"),
+    ("assist", "assist_skills"),
+    ("cultivation", "cultivations"),
+];
+pub const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+fn trash_table(category: &str) -> Result<&'static str, String> {
+    TRASH_TABLES.iter().find(|t| t.0 == category).map(|t| t.1)
+        .ok_or_else(|| format!("校验失败: category: 应为 account、master、assist 或 cultivation，而不是 '{}'", category))
+}
+
+fn trash_retention_days(conn: &Connection) -> Result<i64, String> {
+    let value: Option<String> = match conn.query_row("SELECT value FROM settings WHERE key = 'trash_retention_days'", [], |r| r.get(0)) {
+        Ok(v) => Some(v),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(e.to_string()),
+    };
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_TRASH_RETENTION_DAYS))
+}
+
+// 彻底删除在回收站中超过保留天数的记录，保留天数为 0 时不清理；账号的子记录随外键级联删除
+// 返回各回收站类别清理的记录数，只列出有清理的类别
+fn purge_expired_trash(conn: &Connection) -> Result<Vec<(&'static str, usize)>, String> {
+    let days = trash_retention_days(conn)?;
+    let mut purged = Vec::new();
+    if days == 0 { return Ok(purged); }
+    for &(category, table) in TRASH_TABLES.iter().rev() {
+        let sql = format!("DELETE FROM {} WHERE deleted_at IS NOT NULL AND deleted_at < datetime('now','localtime', ?1)", table);
+        let n = conn.execute(&sql, params![format!("-{} days", days)]).map_err(|e| e.to_string())?;
+        if n > 0 { purged.push((category, n)); }
+    }
+    Ok(purged)
+}
+
+// 回收站中一条记录的所属账号，不在回收站中时报错
+fn trashed_owner(conn: &Connection, category: &str, id: i64) -> Result<i64, String> {
+    let table = trash_table(category)?;
+    let owner = if category == "account" { "id" } else { "account_id" };
+    let sql = format!("SELECT {}, deleted_at FROM {} WHERE id = ?1", owner, table);
+    let (account_id, deleted_at): (i64, Option<String>) = conn.query_row(&sql, params![id], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("记录不存在: {} {}", category, id),
+            e => e.to_string(),
+        })?;
+    if deleted_at.is_none() { return Err(format!("不在回收站中: {} {}", category, id)); }
+    Ok(account_id)
+}
+
+// 把各档案内置的消耗表写入 cost_tables，已存在的版本不覆盖
+fn seed_cost_tables(conn: &Connection) -> Result<(), String> {
+    for p in rules::PROFILES.iter() {
+        let data = serde_json::to_string(&p.builtin_cost_table()).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT OR IGNORE INTO cost_tables (server_type, version, effective_date, data) VALUES (?1, ?2, ?3, ?4)",
+            params![p.server_type, p.cost_version, p.cost_effective_date, data]
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// date 当天生效的消耗表：生效日期不晚于 date 的最新版本；
+// 早于所有版本时取最早一版，库中没有任何版本时退回档案内置表
+fn cost_table_at(conn: &Connection, server_type: &str, date: &str) -> Result<(Option<i64>, CostTable), String> {
+    let optional = |res: rusqlite::Result<(i64, String)>| match res {
+        Ok(r) => Ok(Some(r)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    };
+    let mut row = optional(conn.query_row(
+        "SELECT id, data FROM cost_tables WHERE server_type = ?1 AND active = 1 AND effective_date <= ?2 ORDER BY effective_date DESC, id DESC LIMIT 1",
+        params![server_type, date], |r| Ok((r.get(0)?, r.get(1)?))
+    ))?;
+    if row.is_none() {
+        row = optional(conn.query_row(
+            "SELECT id, data FROM cost_tables WHERE server_type = ?1 AND active = 1 ORDER BY effective_date, id LIMIT 1",
+            params![server_type], |r| Ok((r.get(0)?, r.get(1)?))
+        ))?;
+    }
+    match row {
+        Some((id, data)) => {
+            let mut table: CostTable = serde_json::from_str(&data).map_err(|e| format!("消耗表 {} 数据损坏: {}", id, e))?;
+            // 早期写入的版本不带人物升级经验，沿用档案内置表
+            if table.character_exp.is_empty() { table.character_exp = rules::profile_of(server_type).character_exp.to_vec(); }
+            Ok((Some(id), table))
+        }
+        None => Ok((None, rules::profile_of(server_type).builtin_cost_table())),
+    }
+}
+
+fn read_cost_file(path: &std::path::Path) -> Result<CostTableFile, String> {
+    let format = cost_overrides::format_of(path)?;
+    let content = fs::read_to_string(path).map_err(|e| format!("读取 {} 失败: {}", path.display(), e))?;
+    cost_overrides::parse(&content, &format)
+}
+
+// 把自定义表写成一个新版本；内容与该服务器最新的自定义版本相同时直接复用
+fn apply_cost_file(conn: &Connection, file: &CostTableFile) -> Result<i64, String> {
+    let profile = rules::profile_of(&file.server_type);
+    let data = serde_json::to_string(&cost_overrides::apply(file, profile.builtin_cost_table())).map_err(|e| e.to_string())?;
+    let latest: Option<(i64, String)> = conn.query_row(
+        "SELECT id, data FROM cost_tables WHERE server_type = ?1 AND source = 'custom' AND active = 1 ORDER BY id DESC LIMIT 1",
+        params![profile.server_type], |r| Ok((r.get(0)?, r.get(1)?))
+    ).map(Some).or_else(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => Ok(None),
+        e => Err(e.to_string()),
+    })?;
+    if let Some((id, existing)) = latest {
+        if existing == data { return Ok(id); }
+    }
+    let effective_date = match &file.effective_date { Some(d) => dates::normalize_date(d)?, None => today(conn)? };
+    let version = match &file.version {
+        Some(v) => v.clone(),
+        None => {
+            let n: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM cost_tables WHERE server_type = ?1 AND source = 'custom'", params![profile.server_type], |r| r.get(0)
+            ).map_err(|e| e.to_string())?;
+            format!("{}-custom-{}", profile.server_type, n + 1)
+        }
+    };
+    // 同名版本已存在：内容相同则重新启用（重置后再次导入），否则要求更换版本名
+    let same_name: Option<(i64, String)> = conn.query_row(
+        "SELECT id, data FROM cost_tables WHERE server_type = ?1 AND version = ?2",
+        params![profile.server_type, version], |r| Ok((r.get(0)?, r.get(1)?))
+    ).map(Some).or_else(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => Ok(None),
+        e => Err(e.to_string()),
+    })?;
+    match same_name {
+        Some((id, existing)) if existing == data => {
+            conn.execute("UPDATE cost_tables SET active = 1 WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+            Ok(id)
+        }
+        Some(_) => Err(format!("消耗表版本 '{}' 已存在且内容不同，请更换 version", version)),
+        None => {
+            conn.execute(
+                "INSERT INTO cost_tables (server_type, version, effective_date, data, source) VALUES (?1, ?2, ?3, ?4, 'custom')",
+                params![profile.server_type, version, effective_date, data]
+            ).map_err(|e| e.to_string())?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+// 修炼的收费档位：取账号下同名修炼的当前档位，没有时用该修炼的默认档位
+fn cultivation_mode(conn: &Connection, account_id: i64, name: &str) -> Result<String, String> {
+    let kind = catalog::find_cultivation(name).ok_or_else(|| format!("未知修炼 '{}'", name))?;
+    let mode: Option<String> = conn.query_row(
+        "SELECT mode FROM cultivations WHERE account_id = ?1 AND name = ?2 ORDER BY id LIMIT 1",
+        params![account_id, name], |r| r.get(0)
+    ).optional().map_err(|e| e.to_string())?;
+    Ok(mode.unwrap_or_else(|| kind.default_mode.to_string()))
+}
+
+// 按消耗表计算一次技能/修炼变更的消耗；修炼按收费档位 mode 计价
+fn change_cost(table: &CostTable, log: &NewChangeLog, mode: &str) -> Result<Resources, String> {
+    let from = log.from_level.unwrap_or(0);
+    let to = log.to_level.unwrap_or(from);
+    Ok(match log.category.as_str() {
+        "master" => {
+            let c = table.master_cost(from, to);
+            Resources { exp: c.exp, gold: c.money, ..Default::default() }
+        }
+        "assist" => {
+            let c = table.assist_cost(from, to);
+            Resources { exp: c.exp, gold: c.money, gang: c.gang, ..Default::default() }
+        }
+        "cultivation" => {
+            let kind = catalog::find_cultivation(&log.name).ok_or_else(|| format!("未知修炼 '{}'", log.name))?;
+            let c = table.cultivation_cost_between(kind.r#type, mode, from, log.from_exp.unwrap_or(0) as i64, to, log.to_exp.unwrap_or(0) as i64);
+            Resources { gold: c.money, cultivation_exp: c.exp, ..Default::default() }
+        }
+        other => return Err(format!("类别 '{}' 的记录不支持计算消耗", other)),
+    })
+}
+
+// 写入变更记录并在同一事务内扣除消耗；金钱同时记入消耗记录，统计口径与手动记账一致
+fn insert_change_log(conn: &Connection, account_id: i64, log: &NewChangeLog, cost: &Resources, cost_version_id: Option<i64>, force: bool) -> Result<i64, String> {
+    conn.execute(
+        "INSERT INTO change_logs (account_id, category, name, from_level, to_level, from_exp, to_exp, consumed_exp, consumed_money, consumed_gang, consumed_cultivation_exp, date, cost_version_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![account_id, log.category, log.name, log.from_level, log.to_level, log.from_exp, log.to_exp, cost.exp, cost.gold, cost.gang, cost.cultivation_exp, log.date, cost_version_id]
+    ).map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+    debit_account(conn, account_id, cost, force)?;
+    if cost.gold > 0 {
+        conn.execute(
+            "INSERT INTO spend_logs (account_id, amount, date, note, change_log_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![account_id, cost.gold, log.date, format!("升级 {}", log.name), id]
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(id)
+}
+
+fn cost_table_by_id(conn: &Connection, id: i64) -> Result<CostTable, String> {
+    let data: String = conn.query_row("SELECT data FROM cost_tables WHERE id = ?1", params![id], |r| r.get(0))
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("消耗表版本不存在: {}", id),
+            e => e.to_string(),
+        })?;
+    serde_json::from_str(&data).map_err(|e| format!("消耗表 {} 数据损坏: {}", id, e))
+}
+
+fn skill_index(school: &str, skill_name: &str) -> Option<usize> {
+    catalog::find_school(school).and_then(|s| s.master_skills.iter().position(|n| *n == skill_name))
+}
+
+// 按位置把师门技能从 from 门派改名到 to 门派；不在 from 门派目录中的技能归档到 change_id 下。
+// 改名分两步（先改为临时名），避免两个门派技能名互相交叉时触发 UNIQUE(account_id, skill_name)。
+fn map_master_skills(conn: &Connection, account_id: i64, change_id: i64, from: &str, to: &str) -> Result<(), String> {
+    let to_skills = catalog::find_school(to).map(|s| s.master_skills).ok_or_else(|| format!("未知门派: {}", to))?;
+    let rows: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare("SELECT id, skill_name FROM master_skills WHERE account_id = ?1 AND deleted_at IS NULL ORDER BY id").map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![account_id], |row| Ok((row.get(0)?, row.get(1)?))).map_err(|e| e.to_string())?;
+        let mut v = Vec::new(); for r in rows { v.push(r.map_err(|e| e.to_string())?); } v
+    };
+    let mut renames = Vec::new();
+    for (id, name) in rows {
+        match skill_index(from, &name) {
+            Some(idx) => renames.push((id, to_skills[idx])),
+            None => archive_master_skill(conn, id, change_id, from)?,
+        }
+    }
+    for (id, _) in &renames {
+        conn.execute("UPDATE master_skills SET skill_name = '#' || id WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    }
+    for (id, name) in &renames {
+        check_not_in_trash(conn, "master_skills", account_id, name)?;
+        conn.execute("UPDATE master_skills SET skill_name = ?1 WHERE id = ?2", params![name, id]).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 回收站中的同名技能仍占用 UNIQUE(account_id, skill_name)，新增前提示恢复或彻底删除
+fn check_not_in_trash(conn: &Connection, table: &str, account_id: i64, skill_name: &str) -> Result<(), String> {
+    let sql = format!("SELECT COUNT(*) FROM {} WHERE account_id = ?1 AND skill_name = ?2 AND deleted_at IS NOT NULL", table);
+    let n: i64 = conn.query_row(&sql, params![account_id, skill_name], |r| r.get(0)).map_err(|e| e.to_string())?;
+    if n > 0 { return Err(format!("技能 {} 在回收站中，请先恢复或彻底删除", skill_name)); }
+    Ok(())
+}
+
+fn archive_master_skill(conn: &Connection, id: i64, change_id: i64, school: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO archived_master_skills (account_id, school_change_id, school, skill_name, current_level, target_level) SELECT account_id, ?1, ?2, skill_name, current_level, target_level FROM master_skills WHERE id = ?3",
+        params![change_id, school, id]
+    ).map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM master_skills WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn archive_all_master_skills(conn: &Connection, account_id: i64, change_id: i64, school: &str) -> Result<(), String> {
+    let ids: Vec<i64> = {
+        let mut stmt = conn.prepare("SELECT id FROM master_skills WHERE account_id = ?1 AND deleted_at IS NULL ORDER BY id").map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![account_id], |row| row.get(0)).map_err(|e| e.to_string())?;
+        let mut v = Vec::new(); for r in rows { v.push(r.map_err(|e| e.to_string())?); } v
+    };
+    for id in ids { archive_master_skill(conn, id, change_id, school)?; }
+    Ok(())
+}
+
+fn write_school_change_log(conn: &Connection, account_id: i64, from: &str, to: &str, date: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO change_logs (account_id, category, name, date) VALUES (?1, 'school', ?2, ?3)",
+        params![account_id, format!("{}→{}", from, to), date]
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// 门派转换（需在事务内调用）：map 按位置映射师门技能，archive 归档旧技能并写入新门派 0 级技能。
+fn change_school_in(conn: &Connection, account_id: i64, to: &str, strategy: &str, date: &str) -> Result<i64, String> {
+    let from = DbState::account_school(conn, account_id)?;
+    if from == to { return Err(format!("账号已是{}", to)); }
+    conn.execute(
+        "INSERT INTO school_changes (account_id, from_school, to_school, strategy, date) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![account_id, from, to, strategy, date]
+    ).map_err(|e| e.to_string())?;
+    let change_id = conn.last_insert_rowid();
+    match strategy {
+        "map" => map_master_skills(conn, account_id, change_id, &from, to)?,
+        "archive" => {
+            archive_all_master_skills(conn, account_id, change_id, &from)?;
+            let skills = catalog::find_school(to).map(|s| s.master_skills).unwrap_or_default();
+            for skill_name in skills.iter() {
+                check_not_in_trash(conn, "master_skills", account_id, skill_name)?;
+                conn.execute(
+                    "INSERT INTO master_skills (account_id, skill_name, current_level, target_level) VALUES (?1, ?2, 0, 0)",
+                    params![account_id, skill_name]
+                ).map_err(|e| e.to_string())?;
+            }
+        }
+        _ => return Err(format!("校验失败: strategy: 应为 map 或 archive，而不是 '{}'", strategy)),
+    }
+    conn.execute("UPDATE accounts SET school = ?1 WHERE id = ?2", params![to, account_id]).map_err(|e| e.to_string())?;
+    write_school_change_log(conn, account_id, &from, to, date)?;
+    Ok(change_id)
+}
+
+// account_id 为空时返回全部账号
+fn accounts_in(conn: &Connection, account_id: Option<i64>) -> Result<Vec<Account>, String> {
+    let mut stmt = conn.prepare("SELECT id, name, school, level, experience, gold, gang, cultivation_exp, server_type FROM accounts WHERE (?1 IS NULL OR id = ?1) AND deleted_at IS NULL ORDER BY id").map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![account_id], |row| {
+        Ok(Account{
+            id: row.get(0)?,
+            name: row.get(1)?,
+            school: row.get(2)?,
+            level: row.get(3)?,
+            experience: row.get(4)?,
+            gold: row.get(5)?,
+            gang: row.get(6)?,
+            cultivation_exp: row.get(7)?,
+            server_type: row.get(8)?,
+        })
+    }).map_err(|e| e.to_string())?;
+    let mut v = Vec::new();
+    for r in rows { v.push(r.map_err(|e| e.to_string())?); }
+    Ok(v)
+}
+
+// table 为 master_skills 或 assist_skills
+fn skills_in(conn: &Connection, table: &str, account_id: i64) -> Result<Vec<Skill>, String> {
+    let sql = format!("SELECT id, account_id, skill_name, current_level, target_level FROM {} WHERE account_id = ?1 AND deleted_at IS NULL ORDER BY id", table);
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![account_id], |row| {
+        Ok(Skill{
+            id: row.get(0)?,
+            account_id: row.get(1)?,
+            skill_name: row.get(2)?,
+            current_level: row.get(3)?,
+            target_level: row.get(4)?,
+        })
+    }).map_err(|e| e.to_string())?;
+    let mut v = Vec::new();
+    for r in rows { v.push(r.map_err(|e| e.to_string())?); }
+    Ok(v)
+}
+
+fn cultivations_in(conn: &Connection, account_id: i64) -> Result<Vec<Cultivation>, String> {
+    // Try query including name column first; fallback if column doesn't exist
+    let query_new = "SELECT id, account_id, name, type, mode, current_exp, current_level, target_level, pet_id FROM cultivations WHERE account_id = ?1 AND deleted_at IS NULL ORDER BY id";
+    let mut stmt = match conn.prepare(query_new) {
+        Ok(s) => s,
+        Err(_) => conn.prepare("SELECT id, account_id, type, mode, current_exp, current_level, target_level FROM cultivations WHERE account_id = ?1 AND deleted_at IS NULL ORDER BY id").map_err(|e| e.to_string())?,
+    };
+    let has_name = stmt.column_count() == 9; // with name (and pet_id) it is 9 columns
+    let rows = stmt.query_map(params![account_id], move |row| {
+        if has_name {
+            Ok(Cultivation{
+                id: row.get(0)?,
+                account_id: row.get(1)?,
+                name: row.get(2)?,
+                r#type: row.get(3)?,
+                mode: row.get(4)?,
+                current_exp: row.get(5)?,
+                current_level: row.get(6)?,
+                target_level: row.get(7)?,
+                pet_id: row.get(8)?,
+            })
+        } else {
+            Ok(Cultivation{
+                id: row.get(0)?,
+                account_id: row.get(1)?,
+                name: String::from(""),
+                r#type: row.get(2)?,
+                mode: row.get(3)?,
+                current_exp: row.get(4)?,
+                current_level: row.get(5)?,
+                target_level: row.get(6)?,
+                pet_id: None,
+            })
+        }
+    }).map_err(|e| e.to_string())?;
+    let mut v = Vec::new();
+    for r in rows { v.push(r.map_err(|e| e.to_string())?); }
+    Ok(v)
+}
+
+fn change_logs_in(conn: &Connection, account_id: i64) -> Result<Vec<ChangeLog>, String> {
+    let mut stmt = conn.prepare("SELECT id, account_id, category, name, from_level, to_level, from_exp, to_exp, consumed_exp, consumed_money, consumed_gang, consumed_cultivation_exp, date, created_at, cost_version_id, reverts_id FROM change_logs WHERE account_id = ?1 ORDER BY id DESC").map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![account_id], |row| {
+        Ok(ChangeLog{
+            id: row.get(0)?, account_id: row.get(1)?, category: row.get(2)?, name: row.get(3)?,
+            from_level: row.get(4)?, to_level: row.get(5)?, from_exp: row.get(6)?, to_exp: row.get(7)?,
+            consumed_exp: row.get(8)?, consumed_money: row.get(9)?, consumed_gang: row.get(10)?, consumed_cultivation_exp: row.get(11)?,
+            date: row.get(12)?, created_at: row.get(13)?, cost_version_id: row.get(14)?, reverts_id: row.get(15)?,
+        })
+    }).map_err(|e| e.to_string())?;
+    let mut v = Vec::new(); for r in rows { v.push(r.map_err(|e| e.to_string())?); } Ok(v)
+}
+
+fn get_school_change(conn: &Connection, id: i64) -> Result<SchoolChange, String> {
+    conn.query_row(
+        "SELECT id, account_id, from_school, to_school, strategy, date, reverted, created_at FROM school_changes WHERE id = ?1",
+        params![id],
+        |row| Ok(SchoolChange{
+            id: row.get(0)?, account_id: row.get(1)?, from_school: row.get(2)?, to_school: row.get(3)?,
+            strategy: row.get(4)?, date: row.get(5)?, reverted: row.get::<_, i64>(6)? != 0, created_at: row.get(7)?,
+        })
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("门派转换记录不存在: {}", id),
+        e => e.to_string(),
+    })
 }
 
 impl DbState {
@@ -96,7 +920,7 @@ impl DbState {
             let appdata = env::var("APPDATA").map_err(|e| e.to_string())?; // C:\Users\<User>\AppData\Roaming
             let path = PathBuf::from(appdata).join("mhxy").join("mhxy.db");
             if let Some(parent) = path.parent() { let _ = fs::create_dir_all(parent); }
-            return Ok(path);
+            Ok(path)
         }
 
         #[cfg(target_os = "macos")]
@@ -105,7 +929,7 @@ impl DbState {
             let home = env::var("HOME").map_err(|e| e.to_string())?;
             let path = PathBuf::from(home).join("Library").join("Application Support").join("mhxy").join("mhxy.db");
             if let Some(parent) = path.parent() { let _ = fs::create_dir_all(parent); }
-            return Ok(path);
+            Ok(path)
         }
 
         // Linux / 其他：~/.local/share/mhxy/mhxy.db
@@ -115,14 +939,16 @@ impl DbState {
             let home = env::var("HOME").map_err(|e| e.to_string())?;
             let path = PathBuf::from(home).join(".local").join("share").join("mhxy").join("mhxy.db");
             if let Some(parent) = path.parent() { let _ = fs::create_dir_all(parent); }
-            return Ok(path);
+            Ok(path)
         }
     }
-    pub fn init(&self) -> Result<(), String> {
+    // 返回初始化时记录的警告；已初始化时直接返回，便于多个窗口都能显示
+    pub fn init(&self) -> Result<Vec<String>, String> {
         let mut guard = self.conn.lock().map_err(|e| e.to_string())?;
-        if guard.is_some() { return Ok(()); }
+        let mut warnings = self.warnings.lock().map_err(|e| e.to_string())?;
+        if guard.is_some() { return Ok(warnings.clone()); }
 
-        let db_path = Self::db_path_at_project_root()?;
+        let db_path = self.db_path()?;
         let conn = Connection::open(db_path).map_err(|e| format!("打开数据库失败: {}", e))?;
 
         conn.execute_batch(
@@ -177,7 +1003,7 @@ impl DbState {
             CREATE TABLE IF NOT EXISTS change_logs (
               id INTEGER PRIMARY KEY AUTOINCREMENT,
               account_id INTEGER NOT NULL,
-              category TEXT NOT NULL, -- master/assist/cultivation
+              category TEXT NOT NULL, -- master/assist/cultivation/school
               name TEXT NOT NULL,
               from_level INTEGER,
               to_level INTEGER,
@@ -191,140 +1017,807 @@ impl DbState {
               created_at TEXT NOT NULL DEFAULT (datetime('now','localtime')),
               FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE
             );
-            "#
-        ).map_err(|e| format!("初始化表失败: {}", e))?;
-
-        // 迁移: 尝试添加新列（若已存在则忽略错误）
-        let _ = conn.execute("ALTER TABLE cultivations ADD COLUMN mode TEXT NOT NULL DEFAULT '2w'", []);
-        let _ = conn.execute("ALTER TABLE cultivations ADD COLUMN current_exp INTEGER NOT NULL DEFAULT 0", []);
-        let _ = conn.execute("ALTER TABLE cultivations ADD COLUMN name TEXT NOT NULL DEFAULT ''", []);
-        let _ = conn.execute("ALTER TABLE accounts ADD COLUMN gold INTEGER NOT NULL DEFAULT 0", []);
-        // 回填旧数据的修炼名称，保证前端可直接显示 name
-        let _ = conn.execute(
-            "UPDATE cultivations SET name = CASE WHEN mode='2w' THEN '防御修炼' ELSE '攻击修炼' END WHERE name IS NULL OR name=''",
-            []
-        );
-
-        // 迁移: 去除旧的 UNIQUE(account_id, type) 约束（需要重建表）
-        // 检测并重建（幂等处理，若已迁移会失败则忽略）
-        let _ = conn.execute_batch(
-            r#"
-            BEGIN TRANSACTION;
-            CREATE TABLE IF NOT EXISTS cultivations_v2 (
+            CREATE TABLE IF NOT EXISTS pets (
               id INTEGER PRIMARY KEY AUTOINCREMENT,
               account_id INTEGER NOT NULL,
-              name TEXT NOT NULL DEFAULT '',
-              type TEXT NOT NULL,
-              mode TEXT NOT NULL DEFAULT '2w',
-              current_exp INTEGER NOT NULL DEFAULT 0,
-              current_level INTEGER NOT NULL DEFAULT 0,
-              target_level INTEGER NOT NULL DEFAULT 0,
+              name TEXT NOT NULL,
+              species TEXT NOT NULL DEFAULT '',
+              level INTEGER NOT NULL DEFAULT 0,
+              loyalty INTEGER NOT NULL DEFAULT 100,
+              skills TEXT NOT NULL DEFAULT '[]', -- JSON 数组
+              notes TEXT,
+              created_at TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+              FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE
+            );
+            CREATE TABLE IF NOT EXISTS equipment (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              account_id INTEGER NOT NULL,
+              slot TEXT NOT NULL, -- weapon/helmet/necklace/armour/belt/shoes
+              name TEXT NOT NULL,
+              level INTEGER NOT NULL DEFAULT 0,
+              main_stats TEXT NOT NULL DEFAULT '',
+              special TEXT NOT NULL DEFAULT '', -- 特技/特效
+              gem_level INTEGER NOT NULL DEFAULT 0,
+              gold_spent INTEGER NOT NULL DEFAULT 0,
+              notes TEXT,
+              created_at TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+              FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE
+            );
+            CREATE TABLE IF NOT EXISTS school_changes (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              account_id INTEGER NOT NULL,
+              from_school TEXT NOT NULL,
+              to_school TEXT NOT NULL,
+              strategy TEXT NOT NULL, -- map/archive
+              date TEXT NOT NULL,
+              reverted INTEGER NOT NULL DEFAULT 0,
+              created_at TEXT NOT NULL DEFAULT (datetime('now','localtime')),
               FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE
             );
-            INSERT INTO cultivations_v2 (id, account_id, name, type, mode, current_exp, current_level, target_level)
-              SELECT id, account_id, '' as name, type, mode, current_exp, current_level, target_level FROM cultivations;
-            DROP TABLE cultivations;
-            ALTER TABLE cultivations_v2 RENAME TO cultivations;
-            COMMIT;
+            CREATE TABLE IF NOT EXISTS archived_master_skills (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              account_id INTEGER NOT NULL,
+              school_change_id INTEGER NOT NULL,
+              school TEXT NOT NULL,
+              skill_name TEXT NOT NULL,
+              current_level INTEGER NOT NULL DEFAULT 0,
+              target_level INTEGER NOT NULL DEFAULT 0,
+              FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+              FOREIGN KEY(school_change_id) REFERENCES school_changes(id) ON DELETE CASCADE
+            );
             "#
+        ).map_err(|e| format!("初始化表失败: {}", e))?;
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS cost_tables (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              server_type TEXT NOT NULL,
+              version TEXT NOT NULL,
+              effective_date TEXT NOT NULL,
+              data TEXT NOT NULL, -- JSON，见 costs::CostTable
+              created_at TEXT NOT NULL DEFAULT (datetime('now','localtime')),
+              UNIQUE(server_type, version)
+            );
+            "#
+        ).map_err(|e| format!("初始化表失败: {}", e))?;
+
+        // 迁移: 尝试添加新列（若已存在则忽略错误）
+        let _ = conn.execute("ALTER TABLE cultivations ADD COLUMN mode TEXT NOT NULL DEFAULT '2w'", []);
+        let _ = conn.execute("ALTER TABLE cultivations ADD COLUMN current_exp INTEGER NOT NULL DEFAULT 0", []);
+        let _ = conn.execute("ALTER TABLE cultivations ADD COLUMN name TEXT NOT NULL DEFAULT ''", []);
+        let _ = conn.execute("ALTER TABLE accounts ADD COLUMN gold INTEGER NOT NULL DEFAULT 0", []);
+        let _ = conn.execute("ALTER TABLE accounts ADD COLUMN gang INTEGER NOT NULL DEFAULT 0", []);
+        let _ = conn.execute("ALTER TABLE accounts ADD COLUMN cultivation_exp INTEGER NOT NULL DEFAULT 0", []);
+        let _ = conn.execute("ALTER TABLE accounts ADD COLUMN server_type TEXT NOT NULL DEFAULT 'normal'", []);
+        // 迁移: 已移除的档案（经典服、活动服的未核实数据）改按普通服
+        let known: Vec<String> = rules::PROFILES.iter().map(|p| format!("'{}'", p.server_type)).collect();
+        conn.execute(&format!("UPDATE accounts SET server_type = ?1 WHERE server_type NOT IN ({})", known.join(", ")), params![rules::DEFAULT_SERVER_TYPE])
+            .map_err(|e| format!("初始化表失败: {}", e))?;
+        // 迁移: 去除旧的 UNIQUE(account_id, type) 约束（需要重建表）
+        // 仅当表结构仍带 UNIQUE 时重建，避免每次启动重建而丢失后续新增的列
+        let cultivations_sql: String = conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type='table' AND name='cultivations'", [], |r| r.get(0)
+        ).unwrap_or_default();
+        if cultivations_sql.contains("UNIQUE") {
+            let _ = conn.execute_batch(
+                r#"
+                BEGIN TRANSACTION;
+                CREATE TABLE IF NOT EXISTS cultivations_v2 (
+                  id INTEGER PRIMARY KEY AUTOINCREMENT,
+                  account_id INTEGER NOT NULL,
+                  name TEXT NOT NULL DEFAULT '',
+                  type TEXT NOT NULL,
+                  mode TEXT NOT NULL DEFAULT '2w',
+                  current_exp INTEGER NOT NULL DEFAULT 0,
+                  current_level INTEGER NOT NULL DEFAULT 0,
+                  target_level INTEGER NOT NULL DEFAULT 0,
+                  FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE
+                );
+                INSERT INTO cultivations_v2 (id, account_id, name, type, mode, current_exp, current_level, target_level)
+                  SELECT id, account_id, name, type, mode, current_exp, current_level, target_level FROM cultivations;
+                DROP TABLE cultivations;
+                ALTER TABLE cultivations_v2 RENAME TO cultivations;
+                COMMIT;
+                "#
+            );
+        }
+        // 回填旧数据的修炼名称，保证前端可直接显示 name
+        let _ = conn.execute(
+            "UPDATE cultivations SET name = CASE WHEN mode='2w' THEN '防御修炼' ELSE '攻击修炼' END WHERE name IS NULL OR name=''",
+            []
         );
+        // 迁移: 修炼与消耗记录可关联到具体召唤兽
+        let _ = conn.execute("ALTER TABLE cultivations ADD COLUMN pet_id INTEGER REFERENCES pets(id) ON DELETE SET NULL", []);
+        let _ = conn.execute("ALTER TABLE spend_logs ADD COLUMN pet_id INTEGER REFERENCES pets(id) ON DELETE SET NULL", []);
+        // 迁移: 由变更日志自动生成的消耗记录关联到对应日志
+        let _ = conn.execute("ALTER TABLE spend_logs ADD COLUMN change_log_id INTEGER REFERENCES change_logs(id) ON DELETE SET NULL", []);
+        // 迁移: 消耗记录可关联到具体装备
+        let _ = conn.execute("ALTER TABLE spend_logs ADD COLUMN equipment_id INTEGER REFERENCES equipment(id) ON DELETE SET NULL", []);
+        // 迁移: 变更日志记录计算时使用的消耗表版本
+        let _ = conn.execute("ALTER TABLE change_logs ADD COLUMN cost_version_id INTEGER REFERENCES cost_tables(id)", []);
+        // 迁移: 撤销记录指向被撤销的变更记录
+        let _ = conn.execute("ALTER TABLE change_logs ADD COLUMN reverts_id INTEGER REFERENCES change_logs(id) ON DELETE SET NULL", []);
+        let _ = conn.execute("ALTER TABLE cost_tables ADD COLUMN source TEXT NOT NULL DEFAULT 'builtin'", []);
+        let _ = conn.execute("ALTER TABLE cost_tables ADD COLUMN active INTEGER NOT NULL DEFAULT 1", []);
+        // 迁移: 账号、技能、修炼改为软删除，deleted_at 非空即在回收站中
+        for table in TRASH_TABLES.iter().map(|t| t.1) {
+            let _ = conn.execute(&format!("ALTER TABLE {} ADD COLUMN deleted_at TEXT", table), []);
+        }
+        conn.execute_batch("CREATE TABLE IF NOT EXISTS settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)")
+            .map_err(|e| format!("初始化表失败: {}", e))?;
+        if let Err(e) = purge_expired_trash(&conn) { warnings.push(format!("清理回收站失败: {}", e)); }
+        seed_cost_tables(&conn)?;
+        // 数据目录下有自定义消耗表时载入；文件有误不影响启动，可通过 validate_cost_tables 查看原因
+        if let Some(path) = self.cost_file_path()? {
+            match read_cost_file(&path) {
+                Ok(file) => {
+                    let report = cost_overrides::validate(&file);
+                    if !report.ok {
+                        warnings.push(format!("自定义消耗表 {} 校验未通过，已忽略: {}", path.display(), report.errors.join("; ")));
+                    } else if let Err(e) = apply_cost_file(&conn, &file) {
+                        warnings.push(format!("自定义消耗表 {} 载入失败，已忽略: {}", path.display(), e));
+                    }
+                }
+                Err(e) => warnings.push(format!("自定义消耗表 {} 无法读取，已忽略: {}", path.display(), e)),
+            }
+        }
+
+        // 迁移(一次性): 规范化历史日期，user_version 记录已执行的版本
+        let version: i32 = conn.query_row("PRAGMA user_version", [], |r| r.get(0)).map_err(|e| e.to_string())?;
+        if version < 1 {
+            repair_dates_in(&conn, &Progress::none())?;
+            conn.execute_batch("PRAGMA user_version = 1").map_err(|e| e.to_string())?;
+        }
 
         *guard = Some(conn);
-        Ok(())
+        Ok(warnings.clone())
+    }
+
+    #[cfg(test)]
+    pub fn at(path: PathBuf) -> Self {
+        Self { path: Some(path), ..Self::default() }
+    }
+
+    fn db_path(&self) -> Result<PathBuf, String> {
+        match &self.path {
+            Some(path) => Ok(path.clone()),
+            None => Self::db_path_at_project_root(),
+        }
+    }
+
+    fn data_dir(&self) -> Result<PathBuf, String> {
+        let db_path = self.db_path()?;
+        Ok(db_path.parent().map(|p| p.to_path_buf()).unwrap_or_default())
+    }
+
+    // 数据目录下现有的自定义消耗表文件（json 优先）
+    fn cost_file_path(&self) -> Result<Option<PathBuf>, String> {
+        let dir = self.data_dir()?;
+        Ok(cost_overrides::FILE_FORMATS.iter()
+            .map(|ext| dir.join(format!("{}.{}", cost_overrides::FILE_STEM, ext)))
+            .find(|p| p.exists()))
     }
 
     fn conn(&self) -> Result<Connection, String> {
-        let db_path = Self::db_path_at_project_root()?;
-        Connection::open(db_path).map_err(|e| e.to_string())
+        let db_path = self.db_path()?;
+        let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+        // 外键约束按连接生效，每个连接都需开启，否则级联删除不会发生
+        conn.execute_batch("PRAGMA foreign_keys = ON").map_err(|e| e.to_string())?;
+        Ok(conn)
     }
 
-    // Accounts
-    pub fn add_account(&self, name: String, school: String, level: i32, experience: i64) -> Result<i64, String> {
+    // 记录所属账号，用于变更通知；记录不存在时为空。table 只接受内部常量
+    pub fn account_of(&self, table: &str, id: i64) -> Result<Option<i64>, String> {
+        let sql = format!("SELECT account_id FROM {} WHERE id = ?1", table);
+        match self.conn()?.query_row(&sql, params![id], |r| r.get(0)) {
+            Ok(aid) => Ok(Some(aid)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    // 取消时整个事务回滚，不会只修复一部分
+    pub fn repair_dates(&self, progress: &Progress) -> Result<DateRepairReport, String> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let report = repair_dates_in(&tx, progress)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(report)
+    }
+
+    fn account_school(conn: &Connection, account_id: i64) -> Result<String, String> {
+        conn.query_row("SELECT school FROM accounts WHERE id = ?1 AND deleted_at IS NULL", params![account_id], |row| row.get(0))
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("账号不存在: {}", account_id),
+                e => e.to_string(),
+            })
+    }
+
+    // 账号的规则档案与按人物等级得出的上限
+    fn account_rules(conn: &Connection, account_id: i64) -> Result<AccountRules, String> {
+        let (server_type, level): (String, i32) = conn.query_row(
+            "SELECT server_type, level FROM accounts WHERE id = ?1 AND deleted_at IS NULL", params![account_id], |row| Ok((row.get(0)?, row.get(1)?))
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("账号不存在: {}", account_id),
+            e => e.to_string(),
+        })?;
+        Self::rules_for(conn, &server_type, level)
+    }
+
+    fn rules_for(conn: &Connection, server_type: &str, level: i32) -> Result<AccountRules, String> {
+        let profile = rules::profile_of(server_type);
+        let (_, costs) = cost_table_at(conn, profile.server_type, &today(conn)?)?;
+        Ok(AccountRules { profile, caps: profile.level_caps(level), costs })
+    }
+
+    // 按技能记录找到所属账号的规则档案与上限
+    fn skill_rules(conn: &Connection, table: &str, id: i64) -> Result<AccountRules, String> {
+        let sql = format!("SELECT a.server_type, a.level FROM {} t JOIN accounts a ON a.id = t.account_id WHERE t.id = ?1 AND t.deleted_at IS NULL AND a.deleted_at IS NULL", table);
+        let (server_type, level): (String, i32) = conn.query_row(&sql, params![id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("技能不存在: {}", id),
+                e => e.to_string(),
+            })?;
+        Self::rules_for(conn, &server_type, level)
+    }
+
+    pub fn get_level_caps(&self, account_id: i64) -> Result<rules::LevelCaps, String> {
         let conn = self.conn()?;
-        conn.execute(
-            "INSERT INTO accounts (name, school, level, experience) VALUES (?1, ?2, ?3, ?4)",
-            params![name, school, level, experience]
+        Ok(Self::account_rules(&conn, account_id)?.caps)
+    }
+
+    // Accounts
+    pub fn add_account(&self, a: NewAccount) -> Result<i64, String> {
+        let NewAccount { name, school, server_type, level, experience, gold, master_skills, cultivations } = a;
+        let server_type = server_type.unwrap_or_else(|| rules::DEFAULT_SERVER_TYPE.to_string());
+        validation::validate_account(&name, &school, &server_type, level, experience)?;
+        validation::validate_gold(gold)?;
+        let mut conn = self.conn()?;
+        let r = Self::rules_for(&conn, &server_type, level)?;
+        if let Some(d) = &master_skills {
+            validation::validate_skill(r.profile, None, d.current_level, d.target_level)?;
+            validation::validate_level_cap(d.current_level, r.caps.master_skill, level)?;
+        }
+        for c in &cultivations {
+            validation::validate_cultivation(r.profile, &r.costs, &c.fields())?;
+            validation::validate_level_cap(c.current_level, r.caps.cultivation(&c.r#type), level)?;
+        }
+
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO accounts (name, school, server_type, level, experience, gold) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![name, school, server_type, level, experience, gold]
         ).map_err(|e| e.to_string())?;
-        Ok(conn.last_insert_rowid())
+        let id = tx.last_insert_rowid();
+        if let (Some(d), Some(sch)) = (&master_skills, catalog::find_school(&school)) {
+            for skill_name in sch.master_skills.iter() {
+                tx.execute(
+                    "INSERT INTO master_skills (account_id, skill_name, current_level, target_level) VALUES (?1, ?2, ?3, ?4)",
+                    params![id, skill_name, d.current_level, d.target_level]
+                ).map_err(|e| e.to_string())?;
+            }
+        }
+        for c in &cultivations {
+            tx.execute(
+                "INSERT INTO cultivations (account_id, name, type, mode, current_exp, current_level, target_level) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![id, c.name, c.r#type, c.mode, c.current_exp, c.current_level, c.target_level]
+            ).map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(id)
     }
 
-    pub fn update_account(&self, id: i64, name: String, school: String, level: i32, experience: i64) -> Result<(), String> {
-        let conn = self.conn()?;
-        conn.execute(
-            "UPDATE accounts SET name = ?1, school = ?2, level = ?3, experience = ?4 WHERE id = ?5",
-            params![name, school, level, experience, id]
+    // server_type 为 None 时保持不变
+    pub fn update_account(&self, id: i64, name: String, school: String, server_type: Option<String>, level: i32, experience: i64) -> Result<(), String> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let server_type = match server_type {
+            Some(st) => st,
+            None => Self::account_rules(&tx, id)?.profile.server_type.to_string(),
+        };
+        validation::validate_account(&name, &school, &server_type, level, experience)?;
+        // 门派变化时按位置映射师门技能，避免旧门派技能残留
+        if Self::account_school(&tx, id)? != school {
+            let date = today(&tx)?;
+            change_school_in(&tx, id, &school, "map", &date)?;
+        }
+        tx.execute(
+            "UPDATE accounts SET name = ?1, school = ?2, server_type = ?3, level = ?4, experience = ?5 WHERE id = ?6",
+            params![name, school, server_type, level, experience, id]
         ).map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
         Ok(())
     }
 
+    pub fn change_school(&self, account_id: i64, school: String, strategy: String, date: Option<String>) -> Result<SchoolChange, String> {
+        if !catalog::is_known_school(&school) {
+            return Err(format!("校验失败: school: 未知门派 '{}'", school));
+        }
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let date = match dates::normalize_opt_date(date)? { Some(d) => d, None => today(&tx)? };
+        let change_id = change_school_in(&tx, account_id, &school, &strategy, &date)?;
+        let change = get_school_change(&tx, change_id)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(change)
+    }
+
+    // 撤销门派转换：仅允许撤销该账号最近一次未撤销的转换
+    pub fn revert_school_change(&self, id: i64) -> Result<(), String> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let change = get_school_change(&tx, id)?;
+        if change.reverted { return Err("该门派转换已撤销".to_string()); }
+        let latest: i64 = tx.query_row(
+            "SELECT MAX(id) FROM school_changes WHERE account_id = ?1 AND reverted = 0",
+            params![change.account_id], |r| r.get(0)
+        ).map_err(|e| e.to_string())?;
+        if latest != id { return Err("只能撤销最近一次门派转换".to_string()); }
+        if Self::account_school(&tx, change.account_id)? != change.to_school {
+            return Err(format!("账号当前门派不是{}，无法撤销", change.to_school));
+        }
+
+        match change.strategy.as_str() {
+            "map" => map_master_skills(&tx, change.account_id, id, &change.to_school, &change.from_school)?,
+            _ => archive_all_master_skills(&tx, change.account_id, id, &change.to_school)?,
+        }
+        // 取回转换时归档的旧门派技能；回收站中有同名技能时拒绝，不替用户删除
+        let archived: Vec<String> = {
+            let mut stmt = tx.prepare("SELECT skill_name FROM archived_master_skills WHERE school_change_id = ?1 AND school = ?2 ORDER BY id").map_err(|e| e.to_string())?;
+            let rows = stmt.query_map(params![id, change.from_school], |row| row.get(0)).map_err(|e| e.to_string())?;
+            let mut v = Vec::new(); for r in rows { v.push(r.map_err(|e| e.to_string())?); } v
+        };
+        for name in &archived { check_not_in_trash(&tx, "master_skills", change.account_id, name)?; }
+        tx.execute(
+            "INSERT INTO master_skills (account_id, skill_name, current_level, target_level) SELECT account_id, skill_name, current_level, target_level FROM archived_master_skills WHERE school_change_id = ?1 AND school = ?2 ORDER BY id",
+            params![id, change.from_school]
+        ).map_err(|e| e.to_string())?;
+        tx.execute(
+            "DELETE FROM archived_master_skills WHERE school_change_id = ?1 AND school = ?2",
+            params![id, change.from_school]
+        ).map_err(|e| e.to_string())?;
+        tx.execute("UPDATE accounts SET school = ?1 WHERE id = ?2", params![change.from_school, change.account_id]).map_err(|e| e.to_string())?;
+        tx.execute("UPDATE school_changes SET reverted = 1 WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+        let date = today(&tx)?;
+        write_school_change_log(&tx, change.account_id, &change.to_school, &change.from_school, &date)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn get_school_changes(&self, account_id: i64) -> Result<Vec<SchoolChange>, String> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT id FROM school_changes WHERE account_id = ?1 ORDER BY id DESC").map_err(|e| e.to_string())?;
+        let ids = stmt.query_map(params![account_id], |row| row.get::<_, i64>(0)).map_err(|e| e.to_string())?;
+        let mut v = Vec::new();
+        for id in ids { v.push(get_school_change(&conn, id.map_err(|e| e.to_string())?)?); }
+        Ok(v)
+    }
+
+    // 移入回收站，可用 restore_from_trash 恢复
     pub fn delete_account(&self, id: i64) -> Result<(), String> {
         let conn = self.conn()?;
-        conn.execute("DELETE FROM accounts WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+        conn.execute("UPDATE accounts SET deleted_at = datetime('now','localtime') WHERE id = ?1 AND deleted_at IS NULL", params![id]).map_err(|e| e.to_string())?;
         Ok(())
     }
 
+    // 直接设置账号余额（None 表示不修改）
+    pub fn set_account_balances(&self, account_id: i64, gold: Option<i64>, experience: Option<i64>, gang: Option<i64>, cultivation_exp: Option<i64>) -> Result<Resources, String> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let cur = account_balances(&tx, account_id)?;
+        let next = Resources {
+            exp: experience.unwrap_or(cur.exp),
+            gold: gold.unwrap_or(cur.gold),
+            gang: gang.unwrap_or(cur.gang),
+            cultivation_exp: cultivation_exp.unwrap_or(cur.cultivation_exp),
+        };
+        validation::validate_balances(&next)?;
+        tx.execute(
+            "UPDATE accounts SET experience = ?1, gold = ?2, gang = ?3, cultivation_exp = ?4 WHERE id = ?5",
+            params![next.exp, next.gold, next.gang, next.cultivation_exp, account_id]
+        ).map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(next)
+    }
+
+    pub fn get_level_progress(&self, account_id: i64) -> Result<levels::LevelProgress, String> {
+        let conn = self.conn()?;
+        let (level, experience): (i32, i64) = conn.query_row(
+            "SELECT level, experience FROM accounts WHERE id = ?1 AND deleted_at IS NULL", params![account_id], |r| Ok((r.get(0)?, r.get(1)?))
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("账号不存在: {}", account_id),
+            e => e.to_string(),
+        })?;
+        let server_type = Self::account_rules(&conn, account_id)?.profile.server_type;
+        let (_, table) = cost_table_at(&conn, server_type, &today(&conn)?)?;
+        Ok(levels::level_progress(&table.character_exp, level, experience))
+    }
+
+    // 今天生效的人物升级经验表，未导入时为内置表
+    pub fn character_exp_table(&self, server_type: Option<String>) -> Result<Vec<i64>, String> {
+        let conn = self.conn()?;
+        let profile = rules::profile_of(server_type.as_deref().unwrap_or(rules::DEFAULT_SERVER_TYPE));
+        let (_, table) = cost_table_at(&conn, profile.server_type, &today(&conn)?)?;
+        Ok(table.character_exp)
+    }
+
     pub fn get_all_accounts(&self) -> Result<Vec<Account>, String> {
+        accounts_in(&self.conn()?, None)
+    }
+
+    // 回收站：不在这里清理过期记录，调用方先调用 purge_expired_trash 并为清理的记录发送事件；
+    // account_id 为空时列出全部
+    pub fn list_trash(&self, account_id: Option<i64>) -> Result<Vec<TrashItem>, String> {
         let conn = self.conn()?;
-        let mut stmt = conn.prepare("SELECT id, name, school, level, experience, gold FROM accounts ORDER BY id").map_err(|e| e.to_string())?;
-        let rows = stmt.query_map([], |row| {
-            Ok(Account{
-                id: row.get(0)?,
-                name: row.get(1)?,
-                school: row.get(2)?,
-                level: row.get(3)?,
-                experience: row.get(4)?,
-                gold: row.get(5)?,
-            })
-        }).map_err(|e| e.to_string())?;
+        let days = trash_retention_days(&conn)?;
+        let mut stmt = conn.prepare(
+            "SELECT category, id, account_id, account_name, name, deleted_at, CASE WHEN ?2 > 0 THEN datetime(deleted_at, '+' || ?2 || ' days') END FROM (
+               SELECT 'account' AS category, id, id AS account_id, name AS account_name, name, deleted_at FROM accounts WHERE deleted_at IS NOT NULL
+               UNION ALL SELECT 'master', t.id, t.account_id, a.name, t.skill_name, t.deleted_at FROM master_skills t JOIN accounts a ON a.id = t.account_id WHERE t.deleted_at IS NOT NULL
+               UNION ALL SELECT 'assist', t.id, t.account_id, a.name, t.skill_name, t.deleted_at FROM assist_skills t JOIN accounts a ON a.id = t.account_id WHERE t.deleted_at IS NOT NULL
+               UNION ALL SELECT 'cultivation', t.id, t.account_id, a.name, t.name, t.deleted_at FROM cultivations t JOIN accounts a ON a.id = t.account_id WHERE t.deleted_at IS NOT NULL
+             ) WHERE ?1 IS NULL OR account_id = ?1 ORDER BY deleted_at DESC, category, id"
+        ).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![account_id, days], |r| Ok(TrashItem {
+            category: r.get(0)?, id: r.get(1)?, account_id: r.get(2)?, account_name: r.get(3)?,
+            name: r.get(4)?, deleted_at: r.get(5)?, purge_at: r.get(6)?,
+        })).map_err(|e| e.to_string())?;
         let mut v = Vec::new();
         for r in rows { v.push(r.map_err(|e| e.to_string())?); }
         Ok(v)
     }
 
+    // 从回收站恢复，返回所属账号；技能与修炼需所属账号未被删除，师门技能需属于账号当前门派
+    pub fn restore_from_trash(&self, category: String, id: i64) -> Result<i64, String> {
+        let conn = self.conn()?;
+        let account_id = trashed_owner(&conn, &category, id)?;
+        if category != "account" {
+            let school = Self::account_school(&conn, account_id)
+                .map_err(|_| format!("所属账号 {} 在回收站中，请先恢复账号", account_id))?;
+            if category == "master" {
+                let name: String = conn.query_row("SELECT skill_name FROM master_skills WHERE id = ?1", params![id], |r| r.get(0)).map_err(|e| e.to_string())?;
+                validation::validate_master_skill_name(&school, &name)?;
+            }
+        }
+        let sql = format!("UPDATE {} SET deleted_at = NULL WHERE id = ?1", trash_table(&category)?);
+        conn.execute(&sql, params![id]).map_err(|e| e.to_string())?;
+        Ok(account_id)
+    }
+
+    // 彻底删除回收站中的一条记录，返回所属账号；账号连同其全部数据一起删除
+    pub fn purge_from_trash(&self, category: String, id: i64) -> Result<i64, String> {
+        let conn = self.conn()?;
+        let account_id = trashed_owner(&conn, &category, id)?;
+        let sql = format!("DELETE FROM {} WHERE id = ?1", trash_table(&category)?);
+        conn.execute(&sql, params![id]).map_err(|e| e.to_string())?;
+        Ok(account_id)
+    }
+
+    pub fn get_trash_retention_days(&self) -> Result<i64, String> {
+        trash_retention_days(&self.conn()?)
+    }
+
+    // 彻底删除超过保留天数的回收站记录，返回各类别清理的记录数
+    pub fn purge_expired_trash(&self) -> Result<Vec<(&'static str, usize)>, String> {
+        let conn = self.conn()?;
+        purge_expired_trash(&conn)
+    }
 
-    pub fn add_change_log(&self,
-        account_id: i64,
-        category: String,
-        name: String,
-        from_level: Option<i32>,
-        to_level: Option<i32>,
-        from_exp: Option<i32>,
-        to_exp: Option<i32>,
-        consumed_exp: i64,
-        consumed_money: i64,
-        consumed_gang: i64,
-        consumed_cultivation_exp: i64,
-        date: String,
-    ) -> Result<i64, String> {
+    // 设置回收站保留天数（0 表示不自动清理），新设置立即生效；返回因此清理的各类别记录数
+    pub fn set_trash_retention_days(&self, days: i64) -> Result<Vec<(&'static str, usize)>, String> {
+        validation::validate_retention_days(days)?;
         let conn = self.conn()?;
         conn.execute(
-            "INSERT INTO change_logs (account_id, category, name, from_level, to_level, from_exp, to_exp, consumed_exp, consumed_money, consumed_gang, consumed_cultivation_exp, date) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-            params![account_id, category, name, from_level, to_level, from_exp, to_exp, consumed_exp, consumed_money, consumed_gang, consumed_cultivation_exp, date]
+            "INSERT INTO settings (key, value) VALUES ('trash_retention_days', ?1) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![days.to_string()]
         ).map_err(|e| e.to_string())?;
-        Ok(conn.last_insert_rowid())
+        purge_expired_trash(&conn)
+    }
+
+
+    // 消耗按记录日期生效的消耗表计算，与写入的 cost_version_id 一致，不接受调用方传入的金额
+    pub fn add_change_log(&self, account_id: i64, mut log: NewChangeLog, force: bool) -> Result<i64, String> {
+        log.date = dates::normalize_date(&log.date)?;
+        validation::validate_change(&log.category, log.from_level, log.to_level, log.from_exp, log.to_exp)?;
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let server_type = Self::account_rules(&tx, account_id)?.profile.server_type;
+        let (cost_version_id, table) = cost_table_at(&tx, server_type, &log.date)?;
+        let mode = if log.category == "cultivation" { cultivation_mode(&tx, account_id, &log.name)? } else { String::new() };
+        let cost = change_cost(&table, &log, &mode)?;
+        let id = insert_change_log(&tx, account_id, &log, &cost, cost_version_id, force)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(id)
     }
 
     pub fn get_change_logs(&self, account_id: i64) -> Result<Vec<ChangeLog>, String> {
+        change_logs_in(&self.conn()?, account_id)
+    }
+
+    // 撤销一条技能/修炼变更：恢复到 from_level/from_exp，返还记录的全部消耗并删除对应的消耗记录，
+    // 再写入一条消耗为负数的撤销记录。之后同一技能/修炼还有变更，或当前等级已不是 to_level 时拒绝。
+    // 未扣过余额的早期记录只恢复等级，撤销记录的消耗为 0。
+    pub fn revert_change_log(&self, id: i64) -> Result<ChangeLogRevert, String> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        type Row = (i64, String, String, Option<i32>, Option<i32>, Option<i32>, Option<i32>, Resources, Option<i64>, Option<i64>);
+        let (account_id, category, name, from_level, to_level, from_exp, to_exp, consumed, cost_version_id, reverts_id): Row = tx.query_row(
+            "SELECT account_id, category, name, from_level, to_level, from_exp, to_exp, consumed_exp, consumed_money, consumed_gang, consumed_cultivation_exp, cost_version_id, reverts_id FROM change_logs WHERE id = ?1",
+            params![id], |r| Ok((
+                r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?,
+                Resources { exp: r.get(7)?, gold: r.get(8)?, gang: r.get(9)?, cultivation_exp: r.get(10)? },
+                r.get(11)?, r.get(12)?,
+            ))
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("变更记录不存在: {}", id),
+            e => e.to_string(),
+        })?;
+        if reverts_id.is_some() { return Err(format!("变更记录 {} 本身是撤销记录，不能再撤销", id)); }
+        let table = match category.as_str() {
+            "master" => "master_skills",
+            "assist" => "assist_skills",
+            "cultivation" => "cultivations",
+            "school" => return Err("门派转换请通过 revert_school_change 撤销".to_string()),
+            other => return Err(format!("类别 '{}' 的记录不支持撤销", other)),
+        };
+        let (Some(from), Some(to)) = (from_level, to_level) else {
+            return Err(format!("变更记录 {} 缺少等级，无法撤销", id));
+        };
+        let reverted: i64 = tx.query_row("SELECT COUNT(*) FROM change_logs WHERE reverts_id = ?1", params![id], |r| r.get(0)).map_err(|e| e.to_string())?;
+        if reverted > 0 { return Err(format!("变更记录 {} 已撤销", id)); }
+        // 已撤销的记录与撤销记录成对抵消，不算作之后的变更
+        let later: Option<i64> = tx.query_row(
+            "SELECT MIN(id) FROM change_logs WHERE account_id = ?1 AND category = ?2 AND name = ?3 AND id > ?4
+               AND reverts_id IS NULL AND id NOT IN (SELECT reverts_id FROM change_logs WHERE reverts_id IS NOT NULL)",
+            params![account_id, category, name, id], |r| r.get(0)
+        ).map_err(|e| e.to_string())?;
+        if let Some(later) = later {
+            return Err(format!("{} 之后还有变更记录 {}，请先撤销后面的记录", name, later));
+        }
+
+        let name_col = if category == "cultivation" { "name" } else { "skill_name" };
+        let sql = format!("SELECT id, current_level, {} FROM {} WHERE account_id = ?1 AND {} = ?2 AND deleted_at IS NULL",
+            if category == "cultivation" { "current_exp" } else { "0" }, table, name_col);
+        let targets: Vec<(i64, i32, i32)> = {
+            let mut stmt = tx.prepare(&sql).map_err(|e| e.to_string())?;
+            let rows = stmt.query_map(params![account_id, name], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))).map_err(|e| e.to_string())?;
+            let mut v = Vec::new(); for r in rows { v.push(r.map_err(|e| e.to_string())?); } v
+        };
+        let (target_id, cur_level, cur_exp) = match targets.as_slice() {
+            [t] => *t,
+            [] => return Err(format!("{} 不存在或在回收站中，无法撤销", name)),
+            _ => return Err(format!("账号有多个名为 {} 的修炼，无法确定撤销对象", name)),
+        };
+        if cur_level != to || (category == "cultivation" && to_exp.is_some_and(|e| e != cur_exp)) {
+            return Err(format!("{} 当前为 {} 级，与记录升级后的 {} 级不符，可能已另行修改", name, cur_level, to));
+        }
+        if category == "cultivation" {
+            tx.execute("UPDATE cultivations SET current_level = ?1, current_exp = ?2 WHERE id = ?3", params![from, from_exp.unwrap_or(0), target_id]).map_err(|e| e.to_string())?;
+        } else {
+            let sql = format!("UPDATE {} SET current_level = ?1 WHERE id = ?2", table);
+            tx.execute(&sql, params![from, target_id]).map_err(|e| e.to_string())?;
+        }
+
+        // 早期记录写入时未扣除余额（没有关联的消耗记录，也没有 cost_version_id），只恢复等级，不返还
+        let linked: i64 = tx.query_row("SELECT COUNT(*) FROM spend_logs WHERE change_log_id = ?1", params![id], |r| r.get(0)).map_err(|e| e.to_string())?;
+        let refund = if linked > 0 || cost_version_id.is_some() {
+            Resources { exp: -consumed.exp, gold: -consumed.gold, gang: -consumed.gang, cultivation_exp: -consumed.cultivation_exp }
+        } else {
+            Resources::default()
+        };
+        debit_account(&tx, account_id, &refund, true)?;
+        tx.execute("DELETE FROM spend_logs WHERE change_log_id = ?1", params![id]).map_err(|e| e.to_string())?;
+        let date = today(&tx)?;
+        tx.execute(
+            "INSERT INTO change_logs (account_id, category, name, from_level, to_level, from_exp, to_exp, consumed_exp, consumed_money, consumed_gang, consumed_cultivation_exp, date, cost_version_id, reverts_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![account_id, category, name, to, from, to_exp, from_exp, refund.exp, refund.gold, refund.gang, refund.cultivation_exp, date, cost_version_id, id]
+        ).map_err(|e| e.to_string())?;
+        let revert_id = tx.last_insert_rowid();
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(ChangeLogRevert { revert_id, account_id, category, target_id })
+    }
+
+    // 按今天生效的消耗表重新计算一条升级记录；修炼的收费档位取账号同名修炼的当前档位
+    pub fn recompute_change_log(&self, id: i64) -> Result<ChangeLogRecompute, String> {
         let conn = self.conn()?;
-        let mut stmt = conn.prepare("SELECT id, account_id, category, name, from_level, to_level, from_exp, to_exp, consumed_exp, consumed_money, consumed_gang, consumed_cultivation_exp, date, created_at FROM change_logs WHERE account_id = ?1 ORDER BY id DESC").map_err(|e| e.to_string())?;
-        let rows = stmt.query_map(params![account_id], |row| {
-            Ok(ChangeLog{
-                id: row.get(0)?, account_id: row.get(1)?, category: row.get(2)?, name: row.get(3)?,
-                from_level: row.get(4)?, to_level: row.get(5)?, from_exp: row.get(6)?, to_exp: row.get(7)?,
-                consumed_exp: row.get(8)?, consumed_money: row.get(9)?, consumed_gang: row.get(10)?, consumed_cultivation_exp: row.get(11)?,
-                date: row.get(12)?, created_at: row.get(13)?,
+        type Row = (i64, String, String, Option<i32>, Option<i32>, Option<i32>, Option<i32>, Resources, Option<i64>);
+        let (account_id, category, name, from_level, to_level, from_exp, to_exp, recorded, recorded_version_id): Row = conn.query_row(
+            "SELECT account_id, category, name, from_level, to_level, from_exp, to_exp, consumed_exp, consumed_money, consumed_gang, consumed_cultivation_exp, cost_version_id FROM change_logs WHERE id = ?1",
+            params![id], |r| Ok((
+                r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?,
+                Resources { exp: r.get(7)?, gold: r.get(8)?, gang: r.get(9)?, cultivation_exp: r.get(10)? },
+                r.get(11)?,
+            ))
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("变更记录不存在: {}", id),
+            e => e.to_string(),
+        })?;
+        let server_type = Self::account_rules(&conn, account_id)?.profile.server_type;
+        let (current_version_id, table) = cost_table_at(&conn, server_type, &today(&conn)?)?;
+        if !validation::CHANGE_CATEGORIES.contains(&category.as_str()) {
+            return Err(format!("类别 '{}' 的记录不支持重新计算", category));
+        }
+        let mode = if category == "cultivation" { cultivation_mode(&conn, account_id, &name)? } else { String::new() };
+        let current = change_cost(&table, &NewChangeLog { category, name, from_level, to_level, from_exp, to_exp, date: String::new() }, &mode)?;
+        let diff = Resources {
+            exp: current.exp - recorded.exp,
+            gold: current.gold - recorded.gold,
+            gang: current.gang - recorded.gang,
+            cultivation_exp: current.cultivation_exp - recorded.cultivation_exp,
+        };
+        Ok(ChangeLogRecompute { change_log_id: id, recorded_version_id, recorded, current_version_id, current, diff })
+    }
+
+    // 按 date（默认今天）生效的消耗表计算一组升级的消耗，不写入
+    pub fn quote_upgrades(&self, account_id: i64, items: Vec<UpgradeItem>, date: Option<String>) -> Result<UpgradeQuote, String> {
+        let conn = self.conn()?;
+        let date = match date { Some(d) => dates::normalize_date(&d)?, None => today(&conn)? };
+        let (cost_version_id, prepared) = Self::prepare_upgrades(&conn, account_id, items, &date)?;
+        let mut total = Resources::default();
+        let items = prepared.into_iter().map(|p| {
+            total = planner::plus(&total, &p.cost);
+            UpgradeCost { category: p.log.category, id: p.id, name: p.log.name, cost: p.cost }
+        }).collect();
+        Ok(UpgradeQuote { cost_version_id, items, total })
+    }
+
+    // 在同一事务内更新等级、写入变更记录并扣除消耗，任一项失败则全部不生效；返回变更记录 id
+    pub fn apply_upgrades(&self, account_id: i64, items: Vec<UpgradeItem>, date: Option<String>, force: bool) -> Result<Vec<i64>, String> {
+        if items.is_empty() { return Err("没有需要更新的技能或修炼".to_string()); }
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let date = match date { Some(d) => dates::normalize_date(&d)?, None => today(&tx)? };
+        let (cost_version_id, prepared) = Self::prepare_upgrades(&tx, account_id, items, &date)?;
+        let mut ids = Vec::new();
+        for p in &prepared {
+            if p.table == "cultivations" {
+                tx.execute(
+                    "UPDATE cultivations SET current_level = ?1, current_exp = ?2, target_level = ?3 WHERE id = ?4",
+                    params![p.log.to_level, p.log.to_exp, p.target_level, p.id]
+                ).map_err(|e| e.to_string())?;
+            } else {
+                let sql = format!("UPDATE {} SET current_level = ?1, target_level = ?2 WHERE id = ?3", p.table);
+                tx.execute(&sql, params![p.log.to_level, p.target_level, p.id]).map_err(|e| e.to_string())?;
+            }
+            ids.push(insert_change_log(&tx, account_id, &p.log, &p.cost, cost_version_id, force)?);
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(ids)
+    }
+
+    // 按账号规则逐项校验升级并计算消耗；目标等级低于新等级时随之提高
+    fn prepare_upgrades(conn: &Connection, account_id: i64, items: Vec<UpgradeItem>, date: &str) -> Result<(Option<i64>, Vec<PreparedUpgrade>), String> {
+        let r = Self::account_rules(conn, account_id)?;
+        let (cost_version_id, table) = cost_table_at(conn, r.profile.server_type, date)?;
+        let mut seen = std::collections::HashSet::new();
+        let mut out = Vec::new();
+        for item in items {
+            if !seen.insert((item.category.clone(), item.id)) {
+                return Err(format!("校验失败: items: {} {} 重复", item.category, item.id));
+            }
+            let to = item.to_level;
+            let p = match item.category.as_str() {
+                "master" | "assist" => {
+                    let (t, cap) = if item.category == "master" { ("master_skills", r.caps.master_skill) } else { ("assist_skills", r.caps.assist_skill) };
+                    let sql = format!("SELECT skill_name, current_level, target_level FROM {} WHERE id = ?1 AND account_id = ?2 AND deleted_at IS NULL", t);
+                    let (name, cur, target): (String, i32, i32) = conn.query_row(&sql, params![item.id, account_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+                        .map_err(|e| match e {
+                            rusqlite::Error::QueryReturnedNoRows => format!("技能不存在: {}", item.id),
+                            e => e.to_string(),
+                        })?;
+                    if to <= cur { return Err(format!("校验失败: to_level: {} 应高于当前等级 {}", name, cur)); }
+                    validation::validate_skill(r.profile, None, to, target.max(to))?;
+                    validation::validate_level_cap(to, cap, r.caps.character_level)?;
+                    let log = NewChangeLog {
+                        category: item.category, name, from_level: Some(cur), to_level: Some(to), from_exp: None, to_exp: None, date: date.to_string(),
+                    };
+                    let cost = change_cost(&table, &log, "")?;
+                    PreparedUpgrade { table: t, id: item.id, target_level: target.max(to), log, cost }
+                }
+                "cultivation" => {
+                    type Row = (String, String, String, i32, i32, i32);
+                    let (name, r#type, mode, cur, cur_exp, target): Row = conn.query_row(
+                        "SELECT name, type, mode, current_level, current_exp, target_level FROM cultivations WHERE id = ?1 AND account_id = ?2 AND deleted_at IS NULL",
+                        params![item.id, account_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?))
+                    ).map_err(|e| match e {
+                        rusqlite::Error::QueryReturnedNoRows => format!("修炼不存在: {}", item.id),
+                        e => e.to_string(),
+                    })?;
+                    if (to, item.to_exp) <= (cur, cur_exp) {
+                        return Err(format!("校验失败: to_level: {} 应高于当前 {} 级 {} 经验", name, cur, cur_exp));
+                    }
+                    validation::validate_cultivation(r.profile, &table, &validation::CultivationFields {
+                        name: None, r#type: &r#type, mode: &mode, current_exp: item.to_exp, current_level: to, target_level: target.max(to),
+                    })?;
+                    validation::validate_level_cap(to, r.caps.cultivation(&r#type), r.caps.character_level)?;
+                    let log = NewChangeLog {
+                        category: item.category, name, from_level: Some(cur), to_level: Some(to), from_exp: Some(cur_exp), to_exp: Some(item.to_exp), date: date.to_string(),
+                    };
+                    let cost = change_cost(&table, &log, &mode)?;
+                    PreparedUpgrade { table: "cultivations", id: item.id, target_level: target.max(to), log, cost }
+                }
+                other => return Err(format!("校验失败: category: 应为 master/assist/cultivation，而不是 '{}'", other)),
+            };
+            out.push(p);
+        }
+        Ok((cost_version_id, out))
+    }
+
+    pub fn list_cost_tables(&self, server_type: Option<String>) -> Result<Vec<CostTableVersion>, String> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, server_type, version, effective_date, source, active, created_at FROM cost_tables WHERE ?1 IS NULL OR server_type = ?1 ORDER BY server_type, effective_date, id"
+        ).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![server_type], |row| {
+            Ok(CostTableVersion {
+                id: row.get(0)?, server_type: row.get(1)?, version: row.get(2)?, effective_date: row.get(3)?,
+                source: row.get(4)?, active: row.get(5)?, created_at: row.get(6)?,
             })
         }).map_err(|e| e.to_string())?;
         let mut v = Vec::new(); for r in rows { v.push(r.map_err(|e| e.to_string())?); } Ok(v)
     }
 
+    pub fn get_cost_table(&self, id: i64) -> Result<CostTable, String> {
+        let conn = self.conn()?;
+        cost_table_by_id(&conn, id)
+    }
+
+    // 校验指定文件；不传路径时校验数据目录下当前生效的自定义文件
+    pub fn validate_cost_tables(&self, path: Option<String>) -> Result<CostFileReport, String> {
+        let path = match path {
+            Some(p) => PathBuf::from(p),
+            None => self.cost_file_path()?.ok_or_else(|| "没有自定义消耗表文件".to_string())?,
+        };
+        Ok(match read_cost_file(&path) {
+            Ok(file) => cost_overrides::validate(&file),
+            Err(e) => CostFileReport { ok: false, errors: vec![e], warnings: vec![] },
+        })
+    }
+
+    // 校验通过后复制到数据目录（替换原有自定义文件）并写入新版本
+    // 写库前可取消，写库后不再检查
+    pub fn import_cost_tables(&self, path: String, progress: &Progress) -> Result<CostImportResult, String> {
+        let src = PathBuf::from(&path);
+        progress.step("read", 0, 3)?;
+        let file = read_cost_file(&src)?;
+        progress.step("validate", 1, 3)?;
+        let report = cost_overrides::validate(&file);
+        if !report.ok {
+            return Ok(CostImportResult { report, version_id: None, file: None });
+        }
+        progress.step("apply", 2, 3)?;
+        let conn = self.conn()?;
+        let version_id = apply_cost_file(&conn, &file)?;
+        let dest = self.data_dir()?.join(format!("{}.{}", cost_overrides::FILE_STEM, cost_overrides::format_of(&src)?));
+        if let Some(old) = self.cost_file_path()? {
+            if old != dest { fs::remove_file(&old).map_err(|e| e.to_string())?; }
+        }
+        if src != dest { fs::copy(&src, &dest).map_err(|e| format!("复制到 {} 失败: {}", dest.display(), e))?; }
+        progress.report("done", 3, 3);
+        Ok(CostImportResult { report, version_id: Some(version_id), file: Some(dest.display().to_string()) })
+    }
+
+    // 删除自定义文件并停用所有自定义版本，恢复内置表；已引用这些版本的变更记录不受影响
+    pub fn reset_cost_tables(&self) -> Result<usize, String> {
+        while let Some(path) = self.cost_file_path()? {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+        let conn = self.conn()?;
+        conn.execute("UPDATE cost_tables SET active = 0 WHERE source = 'custom' AND active = 1", []).map_err(|e| e.to_string())
+    }
+
     // Spend logs and gold updates
-    pub fn add_spend_log(&self, account_id: i64, amount: i64, date: String, note: Option<String>) -> Result<i64, String> {
+    pub fn add_spend_log(&self, account_id: i64, amount: i64, date: String, note: Option<String>, pet_id: Option<i64>, equipment_id: Option<i64>) -> Result<i64, String> {
+        let date = dates::normalize_date(&date)?;
         let mut conn = self.conn()?;
         let tx = conn.transaction().map_err(|e| e.to_string())?;
+        // 账号不存在或在回收站中时报错
+        account_balances(&tx, account_id)?;
+        if let Some(pid) = pet_id { Self::check_pet_owner(&tx, pid, account_id)?; }
+        if let Some(eid) = equipment_id { Self::check_equipment_owner(&tx, eid, account_id)?; }
         tx.execute(
-            "INSERT INTO spend_logs (account_id, amount, date, note) VALUES (?1, ?2, ?3, ?4)",
-            params![account_id, amount, date, note]
+            "INSERT INTO spend_logs (account_id, amount, date, note, pet_id, equipment_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![account_id, amount, date, note, pet_id, equipment_id]
         ).map_err(|e| e.to_string())?;
         tx.execute(
             "UPDATE accounts SET gold = gold - ?1 WHERE id = ?2",
@@ -335,18 +1828,22 @@ impl DbState {
         Ok(id)
     }
 
-    pub fn get_spend_logs(&self, account_id: Option<i64>, start: Option<String>, end: Option<String>) -> Result<Vec<SpendLog>, String> {
+    pub fn get_spend_logs(&self, account_id: Option<i64>, start: Option<String>, end: Option<String>, pet_id: Option<i64>, equipment_id: Option<i64>) -> Result<Vec<SpendLog>, String> {
+        let start = dates::normalize_opt_date(start)?;
+        let end = dates::normalize_opt_date(end)?;
         let conn = self.conn()?;
-        let mut query = String::from("SELECT id, account_id, amount, date, note, created_at FROM spend_logs WHERE 1=1");
+        let mut query = String::from("SELECT id, account_id, amount, date, note, pet_id, equipment_id, change_log_id, created_at FROM spend_logs WHERE account_id IN (SELECT id FROM accounts WHERE deleted_at IS NULL)");
         let mut binds: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
         if let Some(aid) = account_id { query.push_str(" AND account_id = ?"); binds.push(Box::new(aid)); }
+        if let Some(pid) = pet_id { query.push_str(" AND pet_id = ?"); binds.push(Box::new(pid)); }
+        if let Some(eid) = equipment_id { query.push_str(" AND equipment_id = ?"); binds.push(Box::new(eid)); }
         if let Some(s) = start { query.push_str(" AND date >= ?"); binds.push(Box::new(s)); }
         if let Some(e) = end { query.push_str(" AND date <= ?"); binds.push(Box::new(e)); }
         query.push_str(" ORDER BY date DESC, id DESC");
         let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
         let params_vec: Vec<&dyn rusqlite::ToSql> = binds.iter().map(|b| &**b as &dyn rusqlite::ToSql).collect();
         let rows = stmt.query_map(rusqlite::params_from_iter(params_vec), |row| {
-            Ok(SpendLog{ id: row.get(0)?, account_id: row.get(1)?, amount: row.get(2)?, date: row.get(3)?, note: row.get(4)?, created_at: row.get(5)? })
+            Ok(SpendLog{ id: row.get(0)?, account_id: row.get(1)?, amount: row.get(2)?, date: row.get(3)?, note: row.get(4)?, pet_id: row.get(5)?, equipment_id: row.get(6)?, change_log_id: row.get(7)?, created_at: row.get(8)? })
         }).map_err(|e| e.to_string())?;
         let mut v = Vec::new();
         for r in rows { v.push(r.map_err(|e| e.to_string())?); }
@@ -354,22 +1851,31 @@ impl DbState {
     }
 
     pub fn get_spend_summary_daily(&self, start: String, end: String) -> Result<Vec<SpendSummary>, String> {
+        let start = dates::normalize_date(&start)?;
+        let end = dates::normalize_date(&end)?;
         let conn = self.conn()?;
-        let mut stmt = conn.prepare("SELECT date, SUM(amount) as total FROM spend_logs WHERE date BETWEEN ?1 AND ?2 GROUP BY date ORDER BY date").map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare("SELECT date, SUM(amount) as total FROM spend_logs WHERE date BETWEEN ?1 AND ?2 AND account_id IN (SELECT id FROM accounts WHERE deleted_at IS NULL) GROUP BY date ORDER BY date").map_err(|e| e.to_string())?;
         let rows = stmt.query_map(params![start, end], |row| { Ok(SpendSummary{ date: row.get(0)?, total: row.get(1)? }) }).map_err(|e| e.to_string())?;
         let mut v = Vec::new(); for r in rows { v.push(r.map_err(|e| e.to_string())?); } Ok(v)
     }
 
     pub fn get_spend_summary_monthly(&self, year: i32) -> Result<Vec<SpendSummary>, String> {
+        dates::validate_year(year)?;
         let conn = self.conn()?;
-        let mut stmt = conn.prepare("SELECT substr(date,1,7) as ym, SUM(amount) as total FROM spend_logs WHERE substr(date,1,4)=?1 GROUP BY ym ORDER BY ym").map_err(|e| e.to_string())?;
-        let rows = stmt.query_map(params![year], |row| { Ok(SpendSummary{ date: row.get(0)?, total: row.get(1)? }) }).map_err(|e| e.to_string())?;
+        let mut stmt = conn.prepare("SELECT substr(date,1,7) as ym, SUM(amount) as total FROM spend_logs WHERE substr(date,1,4)=?1 AND account_id IN (SELECT id FROM accounts WHERE deleted_at IS NULL) GROUP BY ym ORDER BY ym").map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![year.to_string()], |row| { Ok(SpendSummary{ date: row.get(0)?, total: row.get(1)? }) }).map_err(|e| e.to_string())?;
         let mut v = Vec::new(); for r in rows { v.push(r.map_err(|e| e.to_string())?); } Ok(v)
     }
 
     // Master skills
     pub fn add_master_skill(&self, account_id: i64, skill_name: String, current_level: i32, target_level: i32) -> Result<i64, String> {
         let conn = self.conn()?;
+        let r = Self::account_rules(&conn, account_id)?;
+        validation::validate_skill(r.profile, Some(&skill_name), current_level, target_level)?;
+        let school = Self::account_school(&conn, account_id)?;
+        validation::validate_master_skill_name(&school, &skill_name)?;
+        validation::validate_level_cap(current_level, r.caps.master_skill, r.caps.character_level)?;
+        check_not_in_trash(&conn, "master_skills", account_id, &skill_name)?;
         conn.execute(
             "INSERT INTO master_skills (account_id, skill_name, current_level, target_level) VALUES (?1, ?2, ?3, ?4)",
             params![account_id, skill_name, current_level, target_level]
@@ -379,6 +1885,9 @@ impl DbState {
 
     pub fn update_master_skill(&self, id: i64, current_level: i32, target_level: i32) -> Result<(), String> {
         let conn = self.conn()?;
+        let r = Self::skill_rules(&conn, "master_skills", id)?;
+        validation::validate_skill(r.profile, None, current_level, target_level)?;
+        validation::validate_level_cap(current_level, r.caps.master_skill, r.caps.character_level)?;
         conn.execute(
             "UPDATE master_skills SET current_level = ?1, target_level = ?2 WHERE id = ?3",
             params![current_level, target_level, id]
@@ -386,32 +1895,24 @@ impl DbState {
         Ok(())
     }
 
+    // 移入回收站，可用 restore_from_trash 恢复
     pub fn delete_master_skill(&self, id: i64) -> Result<(), String> {
         let conn = self.conn()?;
-        conn.execute("DELETE FROM master_skills WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+        conn.execute("UPDATE master_skills SET deleted_at = datetime('now','localtime') WHERE id = ?1 AND deleted_at IS NULL", params![id]).map_err(|e| e.to_string())?;
         Ok(())
     }
 
     pub fn get_master_skills(&self, account_id: i64) -> Result<Vec<Skill>, String> {
-        let conn = self.conn()?;
-        let mut stmt = conn.prepare("SELECT id, account_id, skill_name, current_level, target_level FROM master_skills WHERE account_id = ?1 ORDER BY id").map_err(|e| e.to_string())?;
-        let rows = stmt.query_map(params![account_id], |row| {
-            Ok(Skill{
-                id: row.get(0)?,
-                account_id: row.get(1)?,
-                skill_name: row.get(2)?,
-                current_level: row.get(3)?,
-                target_level: row.get(4)?,
-            })
-        }).map_err(|e| e.to_string())?;
-        let mut v = Vec::new();
-        for r in rows { v.push(r.map_err(|e| e.to_string())?); }
-        Ok(v)
+        skills_in(&self.conn()?, "master_skills", account_id)
     }
 
     // Assist skills
     pub fn add_assist_skill(&self, account_id: i64, skill_name: String, current_level: i32, target_level: i32) -> Result<i64, String> {
         let conn = self.conn()?;
+        let r = Self::account_rules(&conn, account_id)?;
+        validation::validate_skill(r.profile, Some(&skill_name), current_level, target_level)?;
+        validation::validate_level_cap(current_level, r.caps.assist_skill, r.caps.character_level)?;
+        check_not_in_trash(&conn, "assist_skills", account_id, &skill_name)?;
         conn.execute(
             "INSERT INTO assist_skills (account_id, skill_name, current_level, target_level) VALUES (?1, ?2, ?3, ?4)",
             params![account_id, skill_name, current_level, target_level]
@@ -421,6 +1922,9 @@ impl DbState {
 
     pub fn update_assist_skill(&self, id: i64, current_level: i32, target_level: i32) -> Result<(), String> {
         let conn = self.conn()?;
+        let r = Self::skill_rules(&conn, "assist_skills", id)?;
+        validation::validate_skill(r.profile, None, current_level, target_level)?;
+        validation::validate_level_cap(current_level, r.caps.assist_skill, r.caps.character_level)?;
         conn.execute(
             "UPDATE assist_skills SET current_level = ?1, target_level = ?2 WHERE id = ?3",
             params![current_level, target_level, id]
@@ -428,41 +1932,49 @@ impl DbState {
         Ok(())
     }
 
+    // 移入回收站，可用 restore_from_trash 恢复
     pub fn delete_assist_skill(&self, id: i64) -> Result<(), String> {
         let conn = self.conn()?;
-        conn.execute("DELETE FROM assist_skills WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+        conn.execute("UPDATE assist_skills SET deleted_at = datetime('now','localtime') WHERE id = ?1 AND deleted_at IS NULL", params![id]).map_err(|e| e.to_string())?;
         Ok(())
     }
 
     pub fn get_assist_skills(&self, account_id: i64) -> Result<Vec<Skill>, String> {
-        let conn = self.conn()?;
-        let mut stmt = conn.prepare("SELECT id, account_id, skill_name, current_level, target_level FROM assist_skills WHERE account_id = ?1 ORDER BY id").map_err(|e| e.to_string())?;
-        let rows = stmt.query_map(params![account_id], |row| {
-            Ok(Skill{
-                id: row.get(0)?,
-                account_id: row.get(1)?,
-                skill_name: row.get(2)?,
-                current_level: row.get(3)?,
-                target_level: row.get(4)?,
-            })
-        }).map_err(|e| e.to_string())?;
-        let mut v = Vec::new();
-        for r in rows { v.push(r.map_err(|e| e.to_string())?); }
-        Ok(v)
+        skills_in(&self.conn()?, "assist_skills", account_id)
     }
 
     // Cultivations
-    pub fn add_cultivation(&self, account_id: i64, name: String, r#type: String, mode: String, current_exp: i32, current_level: i32, target_level: i32) -> Result<i64, String> {
+    pub fn add_cultivation(&self, account_id: i64, c: NewCultivation, pet_id: Option<i64>) -> Result<i64, String> {
         let conn = self.conn()?;
+        let r = Self::account_rules(&conn, account_id)?;
+        validation::validate_cultivation(r.profile, &r.costs, &c.fields())?;
+        let NewCultivation { name, r#type, mode, current_exp, current_level, target_level } = c;
+        validation::validate_level_cap(current_level, r.caps.cultivation(&r#type), r.caps.character_level)?;
+        if let Some(pid) = pet_id {
+            if r#type != "pet" { return Err("校验失败: pet_id: 只有宝宝修炼可以关联召唤兽".to_string()); }
+            Self::check_pet_owner(&conn, pid, account_id)?;
+        }
         conn.execute(
-            "INSERT INTO cultivations (account_id, name, type, mode, current_exp, current_level, target_level) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![account_id, name, r#type, mode, current_exp, current_level, target_level]
+            "INSERT INTO cultivations (account_id, name, type, mode, current_exp, current_level, target_level, pet_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![account_id, name, r#type, mode, current_exp, current_level, target_level, pet_id]
         ).map_err(|e| e.to_string())?;
         Ok(conn.last_insert_rowid())
     }
 
     pub fn update_cultivation(&self, id: i64, name: Option<String>, mode: String, current_exp: i32, current_level: i32, target_level: i32) -> Result<(), String> {
         let conn = self.conn()?;
+        let (r#type, server_type, level): (String, String, i32) = conn.query_row(
+            "SELECT c.type, a.server_type, a.level FROM cultivations c JOIN accounts a ON a.id = c.account_id WHERE c.id = ?1 AND c.deleted_at IS NULL AND a.deleted_at IS NULL",
+            params![id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("修炼不存在: {}", id),
+            e => e.to_string(),
+        })?;
+        let r = Self::rules_for(&conn, &server_type, level)?;
+        validation::validate_cultivation(r.profile, &r.costs, &validation::CultivationFields {
+            name: name.as_deref(), r#type: &r#type, mode: &mode, current_exp, current_level, target_level,
+        })?;
+        validation::validate_level_cap(current_level, r.caps.cultivation(&r#type), level)?;
         if let Some(nm) = name {
             conn.execute(
                 "UPDATE cultivations SET name = ?1, mode = ?2, current_exp = ?3, current_level = ?4, target_level = ?5 WHERE id = ?6",
@@ -477,45 +1989,389 @@ impl DbState {
         Ok(())
     }
 
+    // 关联/取消关联召唤兽（仅宝宝修炼）
+    pub fn set_cultivation_pet(&self, id: i64, pet_id: Option<i64>) -> Result<(), String> {
+        let conn = self.conn()?;
+        let (account_id, r#type): (i64, String) = conn.query_row(
+            "SELECT account_id, type FROM cultivations WHERE id = ?1 AND deleted_at IS NULL", params![id], |r| Ok((r.get(0)?, r.get(1)?))
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("修炼不存在: {}", id),
+            e => e.to_string(),
+        })?;
+        if let Some(pid) = pet_id {
+            if r#type != "pet" { return Err("校验失败: pet_id: 只有宝宝修炼可以关联召唤兽".to_string()); }
+            Self::check_pet_owner(&conn, pid, account_id)?;
+        }
+        conn.execute("UPDATE cultivations SET pet_id = ?1 WHERE id = ?2", params![pet_id, id]).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    // 移入回收站，可用 restore_from_trash 恢复
     pub fn delete_cultivation(&self, id: i64) -> Result<(), String> {
         let conn = self.conn()?;
-        conn.execute("DELETE FROM cultivations WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+        conn.execute("UPDATE cultivations SET deleted_at = datetime('now','localtime') WHERE id = ?1 AND deleted_at IS NULL", params![id]).map_err(|e| e.to_string())?;
         Ok(())
     }
 
     pub fn get_cultivations(&self, account_id: i64) -> Result<Vec<Cultivation>, String> {
+        cultivations_in(&self.conn()?, account_id)
+    }
+
+    // 人物修炼与宝宝修炼分组，附带到目标等级的剩余消耗
+    pub fn get_cultivation_summary(&self, account_id: i64) -> Result<CultivationSummary, String> {
+        let costs = Self::account_rules(&self.conn()?, account_id)?.costs;
+        let mut summary = CultivationSummary { person: CultivationGroup::default(), pet: CultivationGroup::default() };
+        for c in self.get_cultivations(account_id)? {
+            let cost = costs.cultivation_cost(&c.r#type, &c.mode, c.current_level, c.current_exp as i64, c.target_level);
+            let group = if c.r#type == "pet" { &mut summary.pet } else { &mut summary.person };
+            group.total_level += c.current_level as i64;
+            group.remaining_exp += cost.exp;
+            group.remaining_money += cost.money;
+            group.rows.push(CultivationWithCost { cultivation: c, remaining_exp: cost.exp, remaining_money: cost.money });
+        }
+        Ok(summary)
+    }
+
+    // Planning and overview
+    // 账号下可升级的技能与修炼，上限取人物等级与消耗表共同限制
+    fn upgrade_candidates(conn: &Connection, account_id: i64) -> Result<(AccountRules, Vec<Candidate>), String> {
+        let r = Self::account_rules(conn, account_id)?;
+        let mut cands = Vec::new();
+        for (category, skills, cap, table_len) in [
+            ("master", skills_in(conn, "master_skills", account_id)?, r.caps.master_skill, r.costs.master.len()),
+            ("assist", skills_in(conn, "assist_skills", account_id)?, r.caps.assist_skill, r.costs.assist.len()),
+        ] {
+            for s in skills {
+                cands.push(Candidate {
+                    category, id: s.id, name: s.skill_name, r#type: String::new(), mode: String::new(),
+                    current_level: s.current_level, current_exp: 0, target_level: s.target_level,
+                    max_level: cap.min(table_len as i32),
+                });
+            }
+        }
+        for c in cultivations_in(conn, account_id)? {
+            let kind_max = catalog::find_cultivation(&c.name).map(|k| k.max_level).unwrap_or(r.profile.max_cultivation_level);
+            let max_level = r.caps.cultivation(&c.r#type).min(kind_max).min(r.costs.cultivation_exp_table(&c.r#type).len() as i32);
+            cands.push(Candidate {
+                category: "cultivation", id: c.id, name: c.name, r#type: c.r#type, mode: c.mode,
+                current_level: c.current_level, current_exp: c.current_exp as i64, target_level: c.target_level, max_level,
+            });
+        }
+        Ok((r, cands))
+    }
+
+    // 账号详情页所需的全部数据，在同一个读事务内读取，保证各部分一致
+    pub fn get_account_bundle(&self, account_id: i64) -> Result<AccountBundle, String> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let account = accounts_in(&tx, Some(account_id))?.pop().ok_or_else(|| format!("账号不存在: {}", account_id))?;
+        let (cost_version_id, _) = cost_table_at(&tx, rules::profile_of(&account.server_type).server_type, &today(&tx)?)?;
+        let (r, cands) = Self::upgrade_candidates(&tx, account_id)?;
+        let remaining = Self::remaining_cost_of(account_id, &r, &cands);
+        let cost_of = |category: &str, id: i64| cands.iter().find(|c| c.category == category && c.id == id)
+            .map(|c| planner::remaining_cost(&r.costs, c)).unwrap_or_default();
+        let with_cost = |category: &str, skills: Vec<Skill>| -> Vec<SkillWithCost> {
+            skills.into_iter().map(|s| {
+                let c = cost_of(category, s.id);
+                SkillWithCost { skill: s, remaining_exp: c.exp, remaining_money: c.gold, remaining_gang: c.gang }
+            }).collect()
+        };
+        let master_skills = with_cost("master", skills_in(&tx, "master_skills", account_id)?);
+        let assist_skills = with_cost("assist", skills_in(&tx, "assist_skills", account_id)?);
+        let cultivations = cultivations_in(&tx, account_id)?.into_iter().map(|c| {
+            let cost = cost_of("cultivation", c.id);
+            CultivationWithCost { cultivation: c, remaining_exp: cost.cultivation_exp, remaining_money: cost.gold }
+        }).collect();
+        let change_logs = change_logs_in(&tx, account_id)?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(AccountBundle { account, caps: r.caps, cost_version_id, master_skills, assist_skills, cultivations, change_logs, remaining })
+    }
+
+    // 到各项目标等级还需的消耗，按类别汇总
+    fn remaining_cost_of(account_id: i64, r: &AccountRules, cands: &[Candidate]) -> RemainingCost {
+        let mut out = RemainingCost { account_id, ..Default::default() };
+        for c in cands {
+            let cost = planner::remaining_cost(&r.costs, c);
+            let group = match (c.category, c.r#type.as_str()) {
+                ("master", _) => &mut out.master,
+                ("assist", _) => &mut out.assist,
+                (_, "pet") => &mut out.pet_cultivation,
+                _ => &mut out.person_cultivation,
+            };
+            *group = planner::plus(group, &cost);
+            out.total = planner::plus(&out.total, &cost);
+        }
+        out
+    }
+
+    pub fn get_account_remaining_cost(&self, account_id: i64) -> Result<RemainingCost, String> {
+        let (r, cands) = Self::upgrade_candidates(&self.conn()?, account_id)?;
+        Ok(Self::remaining_cost_of(account_id, &r, &cands))
+    }
+
+    pub fn get_all_remaining_costs(&self, progress: &Progress) -> Result<FleetRemainingCost, String> {
+        let mut fleet = FleetRemainingCost::default();
+        let accounts = self.get_all_accounts()?;
+        for (i, a) in accounts.iter().enumerate() {
+            progress.step("account", i as u64, accounts.len() as u64)?;
+            let cost = self.get_account_remaining_cost(a.id)?;
+            fleet.total = planner::plus(&fleet.total, &cost.total);
+            fleet.accounts.push(cost);
+        }
+        progress.report("account", accounts.len() as u64, accounts.len() as u64);
+        Ok(fleet)
+    }
+
+    // 账号总览：余额、等级、目标完成度、近 7/30 天消耗与最近一次变更日期，一次返回全部账号
+    pub fn get_overview(&self, progress: &Progress) -> Result<Vec<AccountOverview>, String> {
         let conn = self.conn()?;
-        // Try query including name column first; fallback if column doesn't exist
-        let query_new = "SELECT id, account_id, name, type, mode, current_exp, current_level, target_level FROM cultivations WHERE account_id = ?1 ORDER BY id";
-        let mut stmt = match conn.prepare(query_new) {
-            Ok(s) => s,
-            Err(_) => conn.prepare("SELECT id, account_id, type, mode, current_exp, current_level, target_level FROM cultivations WHERE account_id = ?1 ORDER BY id").map_err(|e| e.to_string())?,
+        let today = today(&conn)?;
+        let since_7 = dates::add_days(&today, -6).unwrap_or_else(|| today.clone());
+        let since_30 = dates::add_days(&today, -29).unwrap_or_else(|| today.clone());
+        let mut spend = std::collections::HashMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT account_id, SUM(CASE WHEN date >= ?1 THEN amount ELSE 0 END), SUM(amount) FROM spend_logs WHERE date BETWEEN ?2 AND ?3 GROUP BY account_id"
+        ).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![since_7, since_30, today], |r| Ok((r.get::<_, i64>(0)?, (r.get::<_, i64>(1)?, r.get::<_, i64>(2)?)))).map_err(|e| e.to_string())?;
+        for r in rows { let (id, v) = r.map_err(|e| e.to_string())?; spend.insert(id, v); }
+        let mut last_change = std::collections::HashMap::new();
+        let mut stmt = conn.prepare("SELECT account_id, MAX(date) FROM change_logs GROUP BY account_id").map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?))).map_err(|e| e.to_string())?;
+        for r in rows { let (id, d) = r.map_err(|e| e.to_string())?; last_change.insert(id, d); }
+
+        let accounts = accounts_in(&conn, None)?;
+        let total = accounts.len() as u64;
+        let mut out = Vec::new();
+        for (i, a) in accounts.into_iter().enumerate() {
+            progress.step("account", i as u64, total)?;
+            let (r, cands) = Self::upgrade_candidates(&conn, a.id)?;
+            // 完成度按等级计：各项 min(当前, 目标) 之和 / 目标之和；没有目标时视为已完成
+            let (done, total) = cands.iter().filter(|c| c.target_level > 0)
+                .fold((0i64, 0i64), |(d, t), c| (d + c.current_level.min(c.target_level) as i64, t + c.target_level as i64));
+            let completion = if total > 0 { done as f64 * 100.0 / total as f64 } else { 100.0 };
+            let (spend_7d, spend_30d) = spend.get(&a.id).copied().unwrap_or((0, 0));
+            out.push(AccountOverview {
+                remaining: Self::remaining_cost_of(a.id, &r, &cands),
+                completion, spend_7d, spend_30d,
+                last_change_date: last_change.remove(&a.id),
+                account: a,
+            });
+        }
+        progress.report("account", total, total);
+        Ok(out)
+    }
+
+    // 在预算内规划各技能、修炼的升级等级，只计算不写库
+    pub fn plan_upgrades(&self, account_id: i64, req: PlanRequest, progress: &Progress) -> Result<UpgradePlan, String> {
+        validation::validate_plan_request(&req)?;
+        progress.step("load", 0, 2)?;
+        let (r, mut cands) = Self::upgrade_candidates(&self.conn()?, account_id)?;
+        // 档位不在当前消耗表中的修炼无法计价，不参与规划
+        cands.retain(|c| c.category != "cultivation" || r.costs.cultivation_unit_price(&c.mode).is_some());
+        progress.step("load", 1, 1)?;
+        planner::plan(&r.costs, cands, &req, progress)
+    }
+
+    // 单个技能/修炼用现有资源最多能升到哪一级
+    pub fn reach_with_budget(&self, category: String, id: i64, available: Available) -> Result<Reach, String> {
+        validation::validate_available(&category, &available)?;
+        let table = match category.as_str() {
+            "master" => "master_skills",
+            "assist" => "assist_skills",
+            _ => "cultivations",
         };
-        let has_name = stmt.column_count() == 8; // with name it is 8 columns
-        let rows = stmt.query_map(params![account_id], move |row| {
-            if has_name {
-                Ok(Cultivation{
-                    id: row.get(0)?,
-                    account_id: row.get(1)?,
-                    name: row.get(2)?,
-                    r#type: row.get(3)?,
-                    mode: row.get(4)?,
-                    current_exp: row.get(5)?,
-                    current_level: row.get(6)?,
-                    target_level: row.get(7)?,
-                })
-            } else {
-                Ok(Cultivation{
-                    id: row.get(0)?,
-                    account_id: row.get(1)?,
-                    name: String::from(""),
-                    r#type: row.get(2)?,
-                    mode: row.get(3)?,
-                    current_exp: row.get(4)?,
-                    current_level: row.get(5)?,
-                    target_level: row.get(6)?,
-                })
+        let sql = format!("SELECT account_id FROM {} WHERE id = ?1 AND deleted_at IS NULL", table);
+        let account_id: i64 = self.conn()?.query_row(&sql, params![id], |r| r.get(0)).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("{}不存在: {}", if category == "cultivation" { "修炼" } else { "技能" }, id),
+            e => e.to_string(),
+        })?;
+        let (r, cands) = Self::upgrade_candidates(&self.conn()?, account_id)?;
+        let c = cands.iter().find(|c| c.category == category && c.id == id).ok_or_else(|| format!("技能不存在: {}", id))?;
+        if category == "cultivation" && r.costs.cultivation_unit_price(&c.mode).is_none() {
+            return Err(format!("修炼 {} 的收费档位 '{}' 不在当前消耗表中，无法计算", id, c.mode));
+        }
+        Ok(planner::reach(&r.costs, c, &available))
+    }
+
+    // 按近期消耗速度预测各账号技能、修炼到达目标等级的日期
+    pub fn forecast_targets(&self, account_id: Option<i64>, days: Option<i64>, progress: &Progress) -> Result<Vec<AccountForecast>, String> {
+        let days = days.unwrap_or(forecast::DEFAULT_WINDOW_DAYS);
+        validation::validate_forecast_days(days)?;
+        let conn = self.conn()?;
+        let today = today(&conn)?;
+        let window_start = dates::add_days(&today, 1 - days).ok_or_else(|| format!("统计天数过大: {}", days))?;
+        let accounts = accounts_in(&conn, account_id)?;
+        let total = accounts.len() as u64;
+        let mut out = Vec::new();
+        for (i, a) in accounts.into_iter().enumerate() {
+            progress.step("account", i as u64, total)?;
+            let mut stmt = conn.prepare("SELECT date, SUM(amount) FROM spend_logs WHERE account_id = ?1 AND date BETWEEN ?2 AND ?3 GROUP BY date").map_err(|e| e.to_string())?;
+            let rows = stmt.query_map(params![a.id, window_start, today], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?))).map_err(|e| e.to_string())?;
+            let mut daily = Vec::new();
+            for r in rows {
+                let (date, total) = r.map_err(|e| e.to_string())?;
+                daily.push((dates::days_between(&window_start, &date)?, total));
             }
+            let rate = forecast::gold_rate(&daily, days);
+
+            let (r, cands) = Self::upgrade_candidates(&conn, a.id)?;
+            let targets = cands.into_iter().filter(|c| c.target_level > c.current_level).map(|c| {
+                let remaining_gold = planner::remaining_cost(&r.costs, &c).gold;
+                forecast::Target { category: c.category, id: c.id, name: c.name, current_level: c.current_level, target_level: c.target_level, remaining_gold }
+            }).collect();
+            let (items, all_targets) = forecast::schedule(&today, a.gold, &rate, targets);
+            out.push(AccountForecast {
+                account_id: a.id, account_name: a.name, gold_balance: a.gold,
+                window_start: window_start.clone(), window_end: today.clone(), observed_days: days,
+                observed_total: daily.iter().map(|d| d.1).sum(),
+                active_days: daily.len() as i64, low_confidence: (daily.len() as i64) < forecast::MIN_ACTIVE_DAYS,
+                rate, items, all_targets,
+            });
+        }
+        if let Some(id) = account_id {
+            if out.is_empty() { return Err(format!("账号不存在: {}", id)); }
+        }
+        progress.report("account", total, total);
+        Ok(out)
+    }
+
+    // Pets
+    fn check_pet_owner(conn: &Connection, pet_id: i64, account_id: i64) -> Result<(), String> {
+        let owner: i64 = conn.query_row("SELECT account_id FROM pets WHERE id = ?1", params![pet_id], |r| r.get(0))
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("召唤兽不存在: {}", pet_id),
+                e => e.to_string(),
+            })?;
+        if owner != account_id { return Err(format!("校验失败: pet_id: 召唤兽 {} 不属于该账号", pet_id)); }
+        Ok(())
+    }
+
+    // 召唤兽、装备所属账号在回收站中时视为不存在，与账号一起隐藏
+    fn live_owner(conn: &Connection, table: &str, what: &str, id: i64) -> Result<i64, String> {
+        let sql = format!("SELECT t.account_id FROM {} t JOIN accounts a ON a.id = t.account_id WHERE t.id = ?1 AND a.deleted_at IS NULL", table);
+        conn.query_row(&sql, params![id], |r| r.get(0)).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("{}不存在: {}", what, id),
+            e => e.to_string(),
+        })
+    }
+
+    pub fn add_pet(&self, account_id: i64, p: PetInput) -> Result<i64, String> {
+        validation::validate_pet(&p.name, p.level, p.loyalty, &p.skills)?;
+        let skills_json = serde_json::to_string(&p.skills).map_err(|e| e.to_string())?;
+        let conn = self.conn()?;
+        // 账号不存在或在回收站中时报错
+        account_balances(&conn, account_id)?;
+        conn.execute(
+            "INSERT INTO pets (account_id, name, species, level, loyalty, skills, notes) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![account_id, p.name, p.species, p.level, p.loyalty, skills_json, p.notes]
+        ).map_err(|e| e.to_string())?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn update_pet(&self, id: i64, p: PetInput) -> Result<(), String> {
+        validation::validate_pet(&p.name, p.level, p.loyalty, &p.skills)?;
+        let skills_json = serde_json::to_string(&p.skills).map_err(|e| e.to_string())?;
+        let conn = self.conn()?;
+        Self::live_owner(&conn, "pets", "召唤兽", id)?;
+        conn.execute(
+            "UPDATE pets SET name = ?1, species = ?2, level = ?3, loyalty = ?4, skills = ?5, notes = ?6 WHERE id = ?7",
+            params![p.name, p.species, p.level, p.loyalty, skills_json, p.notes, id]
+        ).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn delete_pet(&self, id: i64) -> Result<(), String> {
+        let conn = self.conn()?;
+        Self::live_owner(&conn, "pets", "召唤兽", id)?;
+        conn.execute("DELETE FROM pets WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn get_pets(&self, account_id: i64) -> Result<Vec<Pet>, String> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT p.id, p.account_id, p.name, p.species, p.level, p.loyalty, p.skills, p.notes, p.created_at FROM pets p JOIN accounts a ON a.id = p.account_id WHERE p.account_id = ?1 AND a.deleted_at IS NULL ORDER BY p.id").map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![account_id], |row| {
+            let skills: String = row.get(6)?;
+            Ok(Pet{
+                id: row.get(0)?,
+                account_id: row.get(1)?,
+                name: row.get(2)?,
+                species: row.get(3)?,
+                level: row.get(4)?,
+                loyalty: row.get(5)?,
+                skills: serde_json::from_str(&skills).unwrap_or_default(),
+                notes: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        }).map_err(|e| e.to_string())?;
+        let mut v = Vec::new();
+        for r in rows { v.push(r.map_err(|e| e.to_string())?); }
+        Ok(v)
+    }
+
+    // Equipment
+    fn check_equipment_owner(conn: &Connection, equipment_id: i64, account_id: i64) -> Result<(), String> {
+        let owner: i64 = conn.query_row("SELECT account_id FROM equipment WHERE id = ?1", params![equipment_id], |r| r.get(0))
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => format!("装备不存在: {}", equipment_id),
+                e => e.to_string(),
+            })?;
+        if owner != account_id { return Err(format!("校验失败: equipment_id: 装备 {} 不属于该账号", equipment_id)); }
+        Ok(())
+    }
+
+    pub fn add_equipment(&self, account_id: i64, e: EquipmentInput) -> Result<i64, String> {
+        validation::validate_equipment(&e.slot, &e.name, e.level, e.gem_level, e.gold_spent)?;
+        let conn = self.conn()?;
+        // 账号不存在或在回收站中时报错
+        account_balances(&conn, account_id)?;
+        conn.execute(
+            "INSERT INTO equipment (account_id, slot, name, level, main_stats, special, gem_level, gold_spent, notes) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![account_id, e.slot, e.name, e.level, e.main_stats, e.special, e.gem_level, e.gold_spent, e.notes]
+        ).map_err(|e| e.to_string())?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn update_equipment(&self, id: i64, e: EquipmentInput) -> Result<(), String> {
+        validation::validate_equipment(&e.slot, &e.name, e.level, e.gem_level, e.gold_spent)?;
+        let conn = self.conn()?;
+        Self::live_owner(&conn, "equipment", "装备", id)?;
+        conn.execute(
+            "UPDATE equipment SET slot = ?1, name = ?2, level = ?3, main_stats = ?4, special = ?5, gem_level = ?6, gold_spent = ?7, notes = ?8 WHERE id = ?9",
+            params![e.slot, e.name, e.level, e.main_stats, e.special, e.gem_level, e.gold_spent, e.notes, id]
+        ).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn delete_equipment(&self, id: i64) -> Result<(), String> {
+        let conn = self.conn()?;
+        Self::live_owner(&conn, "equipment", "装备", id)?;
+        conn.execute("DELETE FROM equipment WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn get_equipment(&self, account_id: i64) -> Result<Vec<Equipment>, String> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT e.id, e.account_id, e.slot, e.name, e.level, e.main_stats, e.special, e.gem_level, e.gold_spent,
+                    (SELECT COALESCE(SUM(amount), 0) FROM spend_logs WHERE equipment_id = e.id), e.notes, e.created_at
+             FROM equipment e JOIN accounts a ON a.id = e.account_id WHERE e.account_id = ?1 AND a.deleted_at IS NULL ORDER BY e.id"
+        ).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![account_id], |row| {
+            Ok(Equipment{
+                id: row.get(0)?,
+                account_id: row.get(1)?,
+                slot: row.get(2)?,
+                name: row.get(3)?,
+                level: row.get(4)?,
+                main_stats: row.get(5)?,
+                special: row.get(6)?,
+                gem_level: row.get(7)?,
+                gold_spent: row.get(8)?,
+                upgrade_spent: row.get(9)?,
+                notes: row.get(10)?,
+                created_at: row.get(11)?,
+            })
         }).map_err(|e| e.to_string())?;
         let mut v = Vec::new();
         for r in rows { v.push(r.map_err(|e| e.to_string())?); }
@@ -523,4 +2379,135 @@ impl DbState {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 每个测试使用独立的临时数据目录
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mhxy-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn temp_db(name: &str) -> DbState {
+        let st = DbState::at(temp_dir(name).join("mhxy.db"));
+        st.init().unwrap();
+        st
+    }
+
+    fn balances(st: &DbState, account_id: i64) -> (i64, i64, i64, i64) {
+        let b = account_balances(&st.conn().unwrap(), account_id).unwrap();
+        (b.exp, b.gold, b.gang, b.cultivation_exp)
+    }
+
+    fn account_with_skill(st: &DbState) -> (i64, i64) {
+        let aid = st.add_account(NewAccount {
+            name: "测试".into(), school: "龙宫".into(), server_type: None, level: 129,
+            experience: 50_000_000, gold: 5_000_000, master_skills: None, cultivations: Vec::new(),
+        }).unwrap();
+        st.set_account_balances(aid, None, None, Some(1000), None).unwrap();
+        let sid = st.add_assist_skill(aid, "强身术".into(), 30, 60).unwrap();
+        (aid, sid)
+    }
+
+    #[test]
+    fn init_reports_an_ignored_cost_file() {
+        let dir = temp_dir("init-warnings");
+        fs::write(dir.join("cost_tables.json"), r#"{"master": {"rows": [{"level": 2, "exp": 1}]}}"#).unwrap();
+        let st = DbState::at(dir.join("mhxy.db"));
+        let warnings = st.init().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("校验未通过"), "{}", warnings[0]);
+        // 再次初始化仍返回同样的警告
+        assert_eq!(st.init().unwrap(), warnings);
+
+        fs::write(dir.join("cost_tables.json"), "not json").unwrap();
+        let warnings = DbState::at(dir.join("mhxy.db")).init().unwrap();
+        assert!(warnings[0].contains("无法读取"), "{}", warnings[0]);
+    }
 
+    #[test]
+    fn repair_dates_normalizes_and_reports_invalid_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE spend_logs (id INTEGER PRIMARY KEY, date TEXT NOT NULL);
+             CREATE TABLE change_logs (id INTEGER PRIMARY KEY, date TEXT NOT NULL);
+             INSERT INTO spend_logs (date) VALUES ('2024-05-01'), ('2024/5/2'), ('坏日期');
+             INSERT INTO change_logs (date) VALUES ('20240503 08:00:00');"
+        ).unwrap();
+        let report = repair_dates_in(&conn, &Progress::none()).unwrap();
+        let repaired: Vec<_> = report.repaired.iter().map(|f| (f.table.as_str(), f.id, f.to.as_str())).collect();
+        assert_eq!(repaired, [("spend_logs", 2, "2024-05-02"), ("change_logs", 1, "2024-05-03")]);
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!((report.invalid[0].id, report.invalid[0].from.as_str()), (3, "坏日期"));
+        let dates: Vec<String> = conn.prepare("SELECT date FROM spend_logs ORDER BY id").unwrap()
+            .query_map([], |r| r.get(0)).unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(dates, ["2024-05-01", "2024-05-02", "坏日期"]);
+    }
+
+    #[test]
+    fn school_change_refuses_to_overwrite_trashed_skills() {
+        let st = temp_db("school-trash");
+        let aid = st.add_account(NewAccount {
+            name: "测试".into(), school: "龙宫".into(), server_type: None, level: 129, experience: 0, gold: 0,
+            master_skills: Some(MasterSkillDefaults { current_level: 100, target_level: 110 }), cultivations: Vec::new(),
+        }).unwrap();
+        st.conn().unwrap().execute(
+            "INSERT INTO master_skills (account_id, skill_name, current_level, target_level, deleted_at) VALUES (?1, '灵性', 50, 60, datetime('now'))",
+            params![aid],
+        ).unwrap();
+        for strategy in ["map", "archive"] {
+            let err = st.change_school(aid, "普陀山".into(), strategy.into(), None).err().unwrap();
+            assert!(err.contains("回收站"), "{}", err);
+        }
+        let trashed: i64 = st.conn().unwrap()
+            .query_row("SELECT COUNT(*) FROM master_skills WHERE account_id = ?1 AND deleted_at IS NOT NULL", params![aid], |r| r.get(0)).unwrap();
+        assert_eq!(trashed, 1);
+        assert_eq!(st.get_master_skills(aid).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn revert_refunds_what_the_change_log_debited() {
+        let st = temp_db("revert-refund");
+        let (aid, sid) = account_with_skill(&st);
+        let before = balances(&st, aid);
+        st.update_assist_skill(sid, 32, 60).unwrap();
+        let log = st.add_change_log(aid, NewChangeLog {
+            category: "assist".into(), name: "强身术".into(), from_level: Some(30), to_level: Some(32),
+            from_exp: None, to_exp: None, date: "2026-10-10".into(),
+        }, false).unwrap();
+        assert_ne!(balances(&st, aid), before);
+
+        st.revert_change_log(log).unwrap();
+        assert_eq!(balances(&st, aid), before);
+        assert_eq!(st.get_assist_skills(aid).unwrap()[0].current_level, 30);
+        let linked: i64 = st.conn().unwrap()
+            .query_row("SELECT COUNT(*) FROM spend_logs WHERE change_log_id = ?1", params![log], |r| r.get(0)).unwrap();
+        assert_eq!(linked, 0);
+        assert!(st.revert_change_log(log).is_err());
+    }
+
+    #[test]
+    fn revert_of_a_log_that_never_debited_restores_levels_only() {
+        let st = temp_db("revert-legacy");
+        let (aid, sid) = account_with_skill(&st);
+        st.update_assist_skill(sid, 32, 60).unwrap();
+        // 早期写入的记录：有消耗金额，但没有 cost_version_id，也没有关联的消耗记录
+        let conn = st.conn().unwrap();
+        conn.execute(
+            "INSERT INTO change_logs (account_id, category, name, from_level, to_level, consumed_exp, consumed_money, consumed_gang, consumed_cultivation_exp, date)
+             VALUES (?1, 'assist', '强身术', 30, 32, 1000, 2000, 30, 0, '2024-01-01')",
+            params![aid],
+        ).unwrap();
+        let log = conn.last_insert_rowid();
+        let before = balances(&st, aid);
+
+        let revert = st.revert_change_log(log).unwrap().revert_id;
+        assert_eq!(balances(&st, aid), before);
+        assert_eq!(st.get_assist_skills(aid).unwrap()[0].current_level, 30);
+        let r = st.get_change_logs(aid).unwrap().into_iter().find(|l| l.id == revert).unwrap();
+        assert_eq!((r.consumed_exp, r.consumed_money, r.consumed_gang), (0, 0, 0));
+    }
+}
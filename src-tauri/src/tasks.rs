@@ -0,0 +1,85 @@
+// 长操作的进度与取消：前端调用时传入 task_id，后端通过 PROGRESS_EVENT 事件报告进度；
+// 调用 cancel_task(task_id) 后，操作在下一个检查点返回 CANCELLED 错误，已写入的事务不受影响。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub const PROGRESS_EVENT: &str = "mhxy://progress";
+pub const CANCELLED: &str = "操作已取消";
+
+#[derive(Clone, serde::Serialize)]
+pub struct TaskProgress {
+    pub task_id: String,
+    pub stage: String,
+    pub done: u64,
+    pub total: u64,
+}
+
+type Report = Box<dyn Fn(&str, u64, u64) + Send + Sync>;
+
+pub struct Progress {
+    report: Report,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Progress {
+    // 不报告进度，也无法取消
+    pub fn none() -> Self {
+        Self::new(|_, _, _| {}, Arc::default())
+    }
+
+    pub fn new(report: impl Fn(&str, u64, u64) + Send + Sync + 'static, cancelled: Arc<AtomicBool>) -> Self {
+        Self { report: Box::new(report), cancelled }
+    }
+
+    pub fn report(&self, stage: &str, done: u64, total: u64) {
+        (self.report)(stage, done, total);
+    }
+
+    /// 检查点：已取消时返回错误，否则报告进度
+    pub fn step(&self, stage: &str, done: u64, total: u64) -> Result<(), String> {
+        if self.cancelled.load(Ordering::Relaxed) { return Err(CANCELLED.to_string()); }
+        self.report(stage, done, total);
+        Ok(())
+    }
+}
+
+// 正在运行的长操作，按 task_id 记录取消标记
+#[derive(Default)]
+pub struct Tasks {
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+// 登记中的任务；丢弃时（正常结束、出错、panic 或调用方的 future 被丢弃）移除登记
+pub struct RunningTask<'a> {
+    tasks: &'a Tasks,
+    task_id: String,
+    pub cancelled: Arc<AtomicBool>,
+}
+
+impl Drop for RunningTask<'_> {
+    fn drop(&mut self) {
+        self.tasks.finish(&self.task_id);
+    }
+}
+
+impl Tasks {
+    pub fn start(&self, task_id: &str) -> Result<RunningTask<'_>, String> {
+        let mut running = self.running.lock().map_err(|e| e.to_string())?;
+        if running.contains_key(task_id) { return Err(format!("任务已在运行: {}", task_id)); }
+        let flag = Arc::new(AtomicBool::new(false));
+        running.insert(task_id.to_string(), flag.clone());
+        Ok(RunningTask { tasks: self, task_id: task_id.to_string(), cancelled: flag })
+    }
+
+    pub fn finish(&self, task_id: &str) {
+        if let Ok(mut running) = self.running.lock() { running.remove(task_id); }
+    }
+
+    // 任务不存在（已结束或从未开始）时返回 false
+    pub fn cancel(&self, task_id: &str) -> Result<bool, String> {
+        let running = self.running.lock().map_err(|e| e.to_string())?;
+        Ok(running.get(task_id).map(|f| f.store(true, Ordering::Relaxed)).is_some())
+    }
+}
@@ -0,0 +1,206 @@
+// 服务端参数校验：按游戏规则检查账号、技能、修炼，
+// 收集字段级错误后统一返回，格式为 "校验失败: 字段: 原因; 字段: 原因"。
+
+use crate::catalog;
+use crate::costs::CostTable;
+use crate::levels::MAX_CHARACTER_LEVEL;
+use crate::planner::{self, Available, PlanRequest};
+use crate::rules::{self, RuleProfile};
+use crate::state::Resources;
+
+pub const MAX_PET_LEVEL: i32 = 180;
+pub const MAX_EQUIPMENT_LEVEL: i32 = 160;
+pub const MAX_GEM_LEVEL: i32 = 20;
+pub const EQUIPMENT_SLOTS: [&str; 6] = ["weapon", "helmet", "necklace", "armour", "belt", "shoes"];
+pub const CULTIVATION_TYPES: [&str; 2] = ["person", "pet"];
+pub const CHANGE_CATEGORIES: [&str; 3] = ["master", "assist", "cultivation"];
+
+#[derive(serde::Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError { field: field.to_string(), message: message.into() });
+    }
+
+    pub fn check(&mut self, ok: bool, field: &str, message: impl Into<String>) {
+        if !ok { self.error(field, message); }
+    }
+
+    pub fn finish(self) -> Result<(), String> {
+        if self.errors.is_empty() { return Ok(()); }
+        let parts: Vec<String> = self.errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+        Err(format!("校验失败: {}", parts.join("; ")))
+    }
+}
+
+fn check_levels(v: &mut Validator, current_level: i32, target_level: i32, max: i32) {
+    v.check(current_level >= 0, "current_level", "不能小于 0");
+    v.check(current_level <= max, "current_level", format!("不能超过 {}", max));
+    v.check(target_level >= 0, "target_level", "不能小于 0");
+    v.check(target_level <= max, "target_level", format!("不能超过 {}", max));
+    v.check(target_level >= current_level, "target_level", "不能低于当前等级");
+}
+
+pub fn validate_account(name: &str, school: &str, server_type: &str, level: i32, experience: i64) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check(!name.trim().is_empty(), "name", "账号名称不能为空");
+    v.check(catalog::is_known_school(school), "school", format!("未知门派 '{}'", school));
+    v.check(rules::find_profile(server_type).is_some(), "server_type", format!("未知服务器类型 '{}'", server_type));
+    v.check((0..=MAX_CHARACTER_LEVEL).contains(&level), "level", format!("应在 0 到 {} 之间", MAX_CHARACTER_LEVEL));
+    v.check(experience >= 0, "experience", "不能小于 0");
+    v.finish()
+}
+
+pub fn validate_gold(gold: i64) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check(gold >= 0, "gold", "不能小于 0");
+    v.finish()
+}
+
+pub fn validate_balances(r: &Resources) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check(r.exp >= 0, "experience", "不能小于 0");
+    v.check(r.gold >= 0, "gold", "不能小于 0");
+    v.check(r.gang >= 0, "gang", "不能小于 0");
+    v.check(r.cultivation_exp >= 0, "cultivation_exp", "不能小于 0");
+    v.finish()
+}
+
+/// 变更记录只支持技能与修炼，且不能降级（撤销另有专门的命令）
+pub fn validate_change(category: &str, from_level: Option<i32>, to_level: Option<i32>, from_exp: Option<i32>, to_exp: Option<i32>) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check(CHANGE_CATEGORIES.contains(&category), "category", format!("应为 {} 之一，而不是 '{}'", CHANGE_CATEGORIES.join("/"), category));
+    v.check(from_level.is_some() && to_level.is_some(), "to_level", "缺少升级前后的等级");
+    if let (Some(from), Some(to)) = (from_level, to_level) {
+        v.check(from >= 0, "from_level", "不能小于 0");
+        v.check((to, to_exp.unwrap_or(0)) >= (from, from_exp.unwrap_or(0)), "to_level", "不能低于升级前的等级");
+    }
+    v.check(from_exp.unwrap_or(0) >= 0 && to_exp.unwrap_or(0) >= 0, "to_exp", "不能小于 0");
+    v.finish()
+}
+
+pub fn validate_plan_request(req: &PlanRequest) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check(planner::OBJECTIVES.contains(&req.objective.as_str()), "objective", format!("应为 {}，而不是 '{}'", planner::OBJECTIVES.join("/"), req.objective));
+    v.check(req.budget.gold >= 0, "budget.gold", "不能小于 0");
+    v.check(req.budget.exp.unwrap_or(0) >= 0, "budget.exp", "不能小于 0");
+    v.check(req.budget.gang.unwrap_or(0) >= 0, "budget.gang", "不能小于 0");
+    v.check(req.budget.cultivation_exp.unwrap_or(0) >= 0, "budget.cultivation_exp", "不能小于 0");
+    for (key, w) in &req.weights {
+        v.check(w.is_finite() && *w >= 0.0, "weights", format!("{} 的权重应为不小于 0 的数", key));
+    }
+    v.finish()
+}
+
+pub fn validate_available(category: &str, a: &Available) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check(["master", "assist", "cultivation"].contains(&category), "category", format!("应为 master/assist/cultivation，而不是 '{}'", category));
+    v.check(a.gold.is_some() || a.exp.is_some() || a.gang.is_some() || a.cultivation_exp.is_some(), "available", "至少给出一项可用资源");
+    v.check(a.gold.unwrap_or(0) >= 0, "gold", "不能小于 0");
+    v.check(a.exp.unwrap_or(0) >= 0, "exp", "不能小于 0");
+    v.check(a.gang.unwrap_or(0) >= 0, "gang", "不能小于 0");
+    v.check(a.cultivation_exp.unwrap_or(0) >= 0, "cultivation_exp", "不能小于 0");
+    v.finish()
+}
+
+pub fn validate_forecast_days(days: i64) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check((7..=3650).contains(&days), "days", "应在 7 到 3650 之间");
+    v.finish()
+}
+
+// 0 表示不自动清理回收站
+pub fn validate_retention_days(days: i64) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check((0..=3650).contains(&days), "days", "应在 0 到 3650 之间");
+    v.finish()
+}
+
+pub fn validate_skill(profile: &RuleProfile, skill_name: Option<&str>, current_level: i32, target_level: i32) -> Result<(), String> {
+    let mut v = Validator::new();
+    if let Some(nm) = skill_name {
+        v.check(!nm.trim().is_empty(), "skill_name", "技能名称不能为空");
+    }
+    check_levels(&mut v, current_level, target_level, profile.max_skill_level);
+    v.finish()
+}
+
+/// 当前等级受人物等级限制（目标等级可提前规划，只受全局上限约束）
+pub fn validate_level_cap(current_level: i32, cap: i32, character_level: i32) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check(current_level <= cap, "current_level", format!("人物等级 {} 时不能超过 {}", character_level, cap));
+    v.finish()
+}
+
+pub fn validate_master_skill_name(school: &str, skill_name: &str) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check(catalog::is_master_skill_of(school, skill_name), "skill_name", format!("'{}' 不是{}的师门技能", skill_name, school));
+    v.finish()
+}
+
+// 待校验的修炼字段；name 为 None 时（修改时不改名）不检查名称
+#[derive(Clone, Copy)]
+pub struct CultivationFields<'a> {
+    pub name: Option<&'a str>,
+    pub r#type: &'a str,
+    pub mode: &'a str,
+    pub current_exp: i32,
+    pub current_level: i32,
+    pub target_level: i32,
+}
+
+pub fn validate_cultivation(profile: &RuleProfile, costs: &CostTable, c: &CultivationFields) -> Result<(), String> {
+    let CultivationFields { name, r#type, mode, current_exp, current_level, target_level } = *c;
+    let mut v = Validator::new();
+    v.check(CULTIVATION_TYPES.contains(&r#type), "type", format!("应为 person 或 pet，而不是 '{}'", r#type));
+    let mut max = profile.max_cultivation_level;
+    if let Some(nm) = name {
+        match catalog::find_cultivation(nm) {
+            Some(kind) if kind.r#type == r#type => max = max.min(kind.max_level),
+            Some(kind) => v.error("name", format!("'{}' 属于 {} 修炼，与类型 {} 不符", nm, kind.r#type, r#type)),
+            None => v.error("name", format!("未知修炼 '{}'", nm)),
+        }
+    }
+    let modes = costs.cultivation_modes();
+    v.check(modes.contains(&mode), "mode", format!("{}可选 {}，而不是 '{}'", profile.label, modes.join("/"), mode));
+    v.check(current_exp >= 0, "current_exp", "不能小于 0");
+    if current_level < max {
+        // 消耗表缺少该等级数据时为 0，不校验经验
+        let need = costs.cultivation_level_exp(r#type, current_level + 1);
+        v.check(need == 0 || (current_exp as i64) < need, "current_exp", format!("应小于本级所需经验 {}", need));
+    }
+    check_levels(&mut v, current_level, target_level, max);
+    v.finish()
+}
+
+pub fn validate_pet(name: &str, level: i32, loyalty: i32, skills: &[String]) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check(!name.trim().is_empty(), "name", "召唤兽名称不能为空");
+    v.check((0..=MAX_PET_LEVEL).contains(&level), "level", format!("应在 0 到 {} 之间", MAX_PET_LEVEL));
+    v.check((0..=100).contains(&loyalty), "loyalty", "应在 0 到 100 之间");
+    v.check(skills.iter().all(|s| !s.trim().is_empty()), "skills", "技能名称不能为空");
+    let mut seen = std::collections::HashSet::new();
+    v.check(skills.iter().all(|s| seen.insert(s.as_str())), "skills", "技能不能重复");
+    v.finish()
+}
+
+pub fn validate_equipment(slot: &str, name: &str, level: i32, gem_level: i32, gold_spent: i64) -> Result<(), String> {
+    let mut v = Validator::new();
+    v.check(EQUIPMENT_SLOTS.contains(&slot), "slot", format!("应为 {} 之一，而不是 '{}'", EQUIPMENT_SLOTS.join("/"), slot));
+    v.check(!name.trim().is_empty(), "name", "装备名称不能为空");
+    v.check((0..=MAX_EQUIPMENT_LEVEL).contains(&level) && level % 10 == 0, "level", format!("应为 0 到 {} 之间的 10 的倍数", MAX_EQUIPMENT_LEVEL));
+    v.check((0..=MAX_GEM_LEVEL).contains(&gem_level), "gem_level", format!("应在 0 到 {} 之间", MAX_GEM_LEVEL));
+    v.check(gold_spent >= 0, "gold_spent", "不能小于 0");
+    v.finish()
+}
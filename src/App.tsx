@@ -2,7 +2,8 @@ import { useState, useEffect } from 'react'
 import AccountList from './components/AccountList'
 import AccountForm from './components/AccountForm'
 import { getAllAccounts, initDb, Account } from './services/accountService'
-import { ToastHost } from './components/ui/toast'
+import { onDataChanged } from './services/eventService'
+import { ToastHost, showToast } from './components/ui/toast'
 import Button from './components/ui/button'
  
 
@@ -28,7 +29,8 @@ function App() {
   useEffect(() => {
     const setup = async () => {
       try {
-        await initDb()
+        const warnings = await initDb()
+        warnings.forEach(w => showToast(w, 'error'))
         await loadAccounts()
         setDbError(null)
       } catch (error) {
@@ -43,6 +45,15 @@ function App() {
     setup()
   }, [])
 
+  // 后端变更事件（含其他窗口的修改）转为 mhxy:dataChanged，账号增删改时重新加载列表
+  useEffect(() => {
+    const unlisten = onDataChanged(e => {
+      if (e.entity === 'account') loadAccounts()
+      window.dispatchEvent(new CustomEvent('mhxy:dataChanged', { detail: e }))
+    })
+    return () => { unlisten.then(f => f()) }
+  }, [])
+
   const handleAddAccount = () => {
     setEditingAccount(null)
     setShowForm(true)
@@ -1,7 +1,7 @@
 import { useState, useEffect } from 'react'
 import { School, schools } from '../data/schools'
-import { addAccount, updateAccount } from '../services/accountService'
-import type { Account } from '../services/accountService'
+import { addAccount, updateAccount, listRuleProfiles } from '../services/accountService'
+import type { Account, RuleProfile } from '../services/accountService'
 
 interface Props {
   account: Account | null
@@ -15,11 +15,18 @@ export default function AccountForm({ account, onSave, onCancel }: Props) {
   const [level, setLevel] = useState(0)
   const [experience, setExperience] = useState(0)
   const [selectedSchool, setSelectedSchool] = useState<School | null>(null)
+  const [serverType, setServerType] = useState('normal')
+  const [profiles, setProfiles] = useState<RuleProfile[]>([])
+
+  useEffect(() => {
+    listRuleProfiles().then(setProfiles).catch(console.error)
+  }, [])
 
   useEffect(() => {
     if (account) {
       setName(account.name)
       setSchool(account.school)
+      setServerType(account.server_type)
       setLevel(account.level)
       setExperience(account.experience)
       const s = School.getSchoolByName(account.school)
@@ -39,9 +46,9 @@ export default function AccountForm({ account, onSave, onCancel }: Props) {
     
     try {
       if (account) {
-        await updateAccount(account.id, name, school, level, experience)
+        await updateAccount(account.id, name, school, level, experience, serverType)
       } else {
-        await addAccount(name, school, level, experience)
+        await addAccount(name, school, level, experience, { serverType })
       }
       onSave()
     } catch (error) {
@@ -84,6 +91,23 @@ export default function AccountForm({ account, onSave, onCancel }: Props) {
         </select>
       </div>
 
+      <div>
+        <label className="block text-sm font-medium text-gray-700 mb-1">
+          服务器类型
+        </label>
+        <select
+          value={serverType}
+          onChange={(e) => setServerType(e.target.value)}
+          className="w-full px-3 py-2 border border-gray-300 rounded-md focus:outline-none focus:ring-2 focus:ring-blue-500"
+        >
+          {profiles.map((p) => (
+            <option key={p.server_type} value={p.server_type}>
+              {p.label}
+            </option>
+          ))}
+        </select>
+      </div>
+
       {selectedSchool && (
         <div className="bg-blue-50 p-4 rounded-md">
           <h3 className="font-semibold text-blue-900 mb-2">师门技能</h3>
@@ -3,11 +3,7 @@ import AccountDetail from './AccountDetail'
 import { Card, CardHeader, CardContent } from './ui/card'
 import Button from './ui/button'
 import { School } from '../data/schools'
-import { deleteAccount as deleteAccountService, Account } from '../services/accountService'
-import { getMasterSkills, getAssistSkills } from '../services/skillService'
-import { getCultivations } from '../services/cultivationService'
-import SkillCalculator from '../utils/skillCalculator'
-import CultivationCalculator from '../utils/cultivationCalculator'
+import { deleteAccount as deleteAccountService, getOverview, Account, AccountOverview } from '../services/accountService'
 // 已移除 antd，使用 Tailwind 样式
 
 interface Props {
@@ -18,15 +14,10 @@ interface Props {
 
 export default function AccountList({ accounts, onEdit, onRefresh }: Props) {
   const [selectedAccount, setSelectedAccount] = useState<number | null>(null)
-  const [totalsMap, setTotalsMap] = useState<Record<number, {
-    master: { exp: number; money: number },
-    assist: { exp: number; money: number; gang: number },
-    cultivation: { exp: number; money: number },
-    allMoney: number,
-  }>>({})
+  const [overviewMap, setOverviewMap] = useState<Record<number, AccountOverview>>({})
 
   const handleDelete = async (id: number) => {
-    if (confirm('确定要删除这个账号吗？')) {
+    if (confirm('确定要删除这个账号吗？删除后可在回收站中恢复')) {
       try {
         await deleteAccountService(id)
         onRefresh()
@@ -44,45 +35,12 @@ export default function AccountList({ accounts, onEdit, onRefresh }: Props) {
 
   useEffect(() => {
     const loadTotals = async () => {
-      const map: Record<number, any> = {}
-      for (const acc of accounts) {
-        try {
-          const [master, assist, cultivations] = await Promise.all([
-            getMasterSkills(acc.id),
-            getAssistSkills(acc.id),
-            getCultivations(acc.id),
-          ])
-          // 师门
-          let mExp = 0, mMoney = 0
-          master.forEach(s => {
-            const c = SkillCalculator.calculateMasterCost(s.current_level, s.target_level)
-            mExp += c.experience
-            mMoney += c.money
-          })
-          // 辅助
-          let aExp = 0, aMoney = 0, aGang = 0
-          assist.forEach(s => {
-            const c = SkillCalculator.calculateAssistCost(s.current_level, s.target_level)
-            aExp += c.experience
-            aMoney += c.money
-            aGang += c.gang || 0
-          })
-          // 修炼
-          let cExp = 0, cMoney = 0
-          cultivations.forEach(c => {
-            const r = CultivationCalculator.calculateCost(c.current_level, c.target_level, c.mode, c.current_exp)
-            cExp += r.experience
-            cMoney += r.money
-          })
-          map[acc.id] = {
-            master: { exp: mExp, money: mMoney },
-            assist: { exp: aExp, money: aMoney, gang: aGang },
-            cultivation: { exp: cExp, money: cMoney },
-            allMoney: mMoney + aMoney + cMoney,
-          }
-        } catch {}
+      try {
+        const list = await getOverview()
+        setOverviewMap(Object.fromEntries(list.map(o => [o.id, o])))
+      } catch (error) {
+        console.error('加载账号总览失败:', error)
       }
-      setTotalsMap(map)
     }
     if (accounts.length) loadTotals()
     const handler = () => { if (accounts.length) loadTotals() }
@@ -120,21 +78,26 @@ export default function AccountList({ accounts, onEdit, onRefresh }: Props) {
                 </CardHeader>
                 <CardContent className="pt-3 pb-4">
                   <div className="text-[13px] text-gray-600 pl-3">等级 {account.level} · 经验 {account.experience.toLocaleString()}</div>
-                  {totalsMap[account.id] && (
-                    <div className="mt-3 space-y-2 pl-3">
-                      <div className="text-[13px] text-gray-800 leading-5">师门：经验 {toWanInt(totalsMap[account.id].master.exp)} 万 · 金钱 {toWanInt(totalsMap[account.id].master.money)} 万</div>
-                      <div className="text-[13px] text-gray-800 leading-5">辅助：经验 {toWanInt(totalsMap[account.id].assist.exp)} 万 · 金钱 {toWanInt(totalsMap[account.id].assist.money)} 万 · 帮贡 {totalsMap[account.id].assist.gang.toLocaleString()}</div>
-                      <div className="text-[13px] text-gray-800 leading-5">修炼：修炼经验 {toWanInt(totalsMap[account.id].cultivation.exp)} 万 · 金钱 {toWanInt(totalsMap[account.id].cultivation.money)} 万</div>
-                      <div className="mt-3 flex flex-wrap gap-2">
-                        <div className="inline-flex items-center gap-2 rounded-md border border-blue-200 bg-blue-50 px-3 py-1.5 text-[13px] font-semibold text-blue-900">
-                          合计经验 <span className="text-blue-700">{toWanInt(totalsMap[account.id].master.exp + totalsMap[account.id].assist.exp + totalsMap[account.id].cultivation.exp)} 万</span>
-                        </div>
-                        <div className="inline-flex items-center gap-2 rounded-md border border-emerald-200 bg-emerald-50 px-3 py-1.5 text-[13px] font-semibold text-emerald-900">
-                          合计金钱 <span className="text-emerald-700">{toWanInt(totalsMap[account.id].allMoney)} 万</span>
+                  {overviewMap[account.id] && (() => {
+                    const o = overviewMap[account.id]
+                    const r = o.remaining
+                    return (
+                      <div className="mt-3 space-y-2 pl-3">
+                        <div className="text-[13px] text-gray-600 leading-5">目标完成 {o.completion.toFixed(1)}% · 近7天消耗 {toWanInt(o.spend_7d)} 万 · 近30天 {toWanInt(o.spend_30d)} 万{o.last_change_date ? ` · 最近变更 ${o.last_change_date}` : ''}</div>
+                        <div className="text-[13px] text-gray-800 leading-5">师门：经验 {toWanInt(r.master.exp)} 万 · 金钱 {toWanInt(r.master.gold)} 万</div>
+                        <div className="text-[13px] text-gray-800 leading-5">辅助：经验 {toWanInt(r.assist.exp)} 万 · 金钱 {toWanInt(r.assist.gold)} 万 · 帮贡 {r.assist.gang.toLocaleString()}</div>
+                        <div className="text-[13px] text-gray-800 leading-5">修炼：修炼经验 {toWanInt(r.person_cultivation.cultivation_exp + r.pet_cultivation.cultivation_exp)} 万 · 金钱 {toWanInt(r.person_cultivation.gold + r.pet_cultivation.gold)} 万</div>
+                        <div className="mt-3 flex flex-wrap gap-2">
+                          <div className="inline-flex items-center gap-2 rounded-md border border-blue-200 bg-blue-50 px-3 py-1.5 text-[13px] font-semibold text-blue-900">
+                            合计经验 <span className="text-blue-700">{toWanInt(r.master.exp + r.assist.exp + r.person_cultivation.cultivation_exp + r.pet_cultivation.cultivation_exp)} 万</span>
+                          </div>
+                          <div className="inline-flex items-center gap-2 rounded-md border border-emerald-200 bg-emerald-50 px-3 py-1.5 text-[13px] font-semibold text-emerald-900">
+                            合计金钱 <span className="text-emerald-700">{toWanInt(r.total.gold)} 万</span>
+                          </div>
                         </div>
                       </div>
-                    </div>
-                  )}
+                    )
+                  })()}
                 </CardContent>
               </Card>
             ))}
@@ -1,5 +1,7 @@
 import { useEffect, useState } from 'react'
-import { getChangeLogs, ChangeLog } from '../services/changeLogService'
+import { getChangeLogs, revertChangeLog, ChangeLog } from '../services/changeLogService'
+import { showToast } from './ui/toast'
+import Button from './ui/button'
 
 export default function AccountLogTab({ accountId }: { accountId: number }) {
   const [logs, setLogs] = useState<ChangeLog[]>([])
@@ -10,8 +12,25 @@ export default function AccountLogTab({ accountId }: { accountId: number }) {
       setLogs(data)
     }
     load()
+    window.addEventListener('mhxy:dataChanged', load)
+    return () => window.removeEventListener('mhxy:dataChanged', load)
   }, [accountId])
 
+  const revertedIds = new Set(logs.map(l => l.reverts_id).filter(id => id != null))
+  const canRevert = (l: ChangeLog) =>
+    l.category !== 'school' && l.reverts_id == null && !revertedIds.has(l.id)
+
+  const handleRevert = async (l: ChangeLog) => {
+    if (!confirm(`撤销 ${l.name} 的这次变更？等级将恢复并返还消耗`)) return
+    try {
+      await revertChangeLog(l.id)
+      showToast('已撤销', 'success')
+    } catch (e) {
+      console.error('撤销变更失败:', e)
+      showToast(String(e), 'error')
+    }
+  }
+
   return (
     <div className="bg-white border border-gray-200 rounded-md p-3">
       <h3 className="text-base font-semibold pl-3 mb-3">变更日志</h3>
@@ -28,6 +47,7 @@ export default function AccountLogTab({ accountId }: { accountId: number }) {
               <th className="text-right px-3 py-2">金钱</th>
               <th className="text-right px-3 py-2">帮贡</th>
               <th className="text-right px-3 py-2">修炼经验</th>
+              <th className="px-3 py-2"></th>
             </tr>
           </thead>
           <tbody>
@@ -38,17 +58,21 @@ export default function AccountLogTab({ accountId }: { accountId: number }) {
                   const map: Record<string, string> = { master: '师门技能', assist: '辅助技能', cultivation: '修炼' }
                   return map[l.category] || l.category
                 })()}</td>
-                <td className="px-3 py-2">{l.name}</td>
+                <td className="px-3 py-2">{l.name}{l.reverts_id != null && <span className="ml-1 text-xs text-gray-500">（撤销）</span>}</td>
                 <td className="px-3 py-2">{l.from_level ?? l.from_exp ?? '-'}</td>
                 <td className="px-3 py-2">{l.to_level ?? l.to_exp ?? '-'}</td>
                 <td className="px-3 py-2 text-right">{(l.consumed_exp || 0).toLocaleString()}</td>
                 <td className="px-3 py-2 text-right">{(l.consumed_money || 0).toLocaleString()}</td>
                 <td className="px-3 py-2 text-right">{(l.consumed_gang || 0).toLocaleString()}</td>
                 <td className="px-3 py-2 text-right">{(l.consumed_cultivation_exp || 0).toLocaleString()}</td>
+                <td className="px-3 py-2 text-right">
+                  {canRevert(l) && <Button size="sm" variant="outline" onClick={() => handleRevert(l)}>撤销</Button>}
+                  {revertedIds.has(l.id) && <span className="text-xs text-gray-500">已撤销</span>}
+                </td>
               </tr>
             ))}
             {logs.length===0 && (
-              <tr><td className="px-3 py-4 text-center text-gray-500" colSpan={9}>暂无日志</td></tr>
+              <tr><td className="px-3 py-4 text-center text-gray-500" colSpan={10}>暂无日志</td></tr>
             )}
           </tbody>
         </table>
@@ -4,9 +4,10 @@ import {
   addCultivation as addCultivationService,
   updateCultivation as updateCultivationService,
   deleteCultivation as deleteCultivationService,
-  getCultivations as getCultivationsService,
   Cultivation,
 } from '../services/cultivationService'
+import { getAccountBundle, CultivationWithCost } from '../services/accountService'
+import type { ResourceAmounts } from '../services/changeLogService'
 import type { CultivationMode } from '../data/cultivationCosts'
 import { Dialog } from './ui/dialog'
 import { showToast } from './ui/toast'
@@ -20,7 +21,8 @@ interface Props {
 const TITLE = '人物修炼'
 
 export default function CultivationManager({ accountId, onRefresh: _onRefresh }: Props) {
-  const [cultivations, setCultivations] = useState<Cultivation[]>([])
+  const [cultivations, setCultivations] = useState<CultivationWithCost[]>([])
+  const [totals, setTotals] = useState<ResourceAmounts>({ exp: 0, gold: 0, gang: 0, cultivation_exp: 0 })
   const [showForm, setShowForm] = useState(false)
   const [editingCultivation, setEditingCultivation] = useState<Cultivation | null>(null)
   const [confirmDeleteId, setConfirmDeleteId] = useState<number | null>(null)
@@ -38,8 +40,10 @@ export default function CultivationManager({ accountId, onRefresh: _onRefresh }:
 
   const loadCultivations = async () => {
     try {
-      const result = await getCultivationsService(accountId)
-      setCultivations(result as Cultivation[])
+      // 每行的剩余消耗与合计都取自后端同一快照
+      const bundle = await getAccountBundle(accountId)
+      setCultivations(bundle.cultivations)
+      setTotals(bundle.remaining.person_cultivation)
     } catch (error) {
       console.error('加载修炼失败:', error)
     }
@@ -74,7 +78,6 @@ export default function CultivationManager({ accountId, onRefresh: _onRefresh }:
         await addCultivationService(accountId, formState.name, 'person', formState.mode, currentExpNum, currentLevelNum, targetLevelNum)
       }
       await loadCultivations()
-      window.dispatchEvent(new Event('mhxy:dataChanged'))
       setShowForm(false)
       setEditingCultivation(null)
     } catch (error) {
@@ -87,27 +90,13 @@ export default function CultivationManager({ accountId, onRefresh: _onRefresh }:
     try {
       await deleteCultivationService(id)
       await loadCultivations()
-      window.dispatchEvent(new Event('mhxy:dataChanged'))
-      showToast('删除成功', 'success')
+      showToast('已移入回收站', 'success')
     } catch (error) {
       console.error('删除修炼失败:', error)
       showToast('删除修炼失败', 'error')
     }
   }
 
-  const getTotalCost = (type: 'person' | 'pet') => {
-    let totalExp = 0
-    let totalMoney = 0
-    cultivations
-      .filter(c => c.type === type)
-      .forEach(cultivation => {
-        const cost = CultivationCalculator.calculateCost(cultivation.current_level, cultivation.target_level, cultivation.mode, cultivation.current_exp)
-        totalExp += cost.experience
-        totalMoney += cost.money
-      })
-    return { experience: totalExp, money: totalMoney }
-  }
-
   return (
     <div>
       <div className="flex justify-between items-center mb-4">
@@ -205,7 +194,6 @@ export default function CultivationManager({ accountId, onRefresh: _onRefresh }:
 
       {(() => {
         const typeCultivations = cultivations.filter(c => c.type === 'person')
-        const totals = getTotalCost('person')
         return (
           <div className="mb-4">
             <h4 className="font-semibold text-gray-700 mb-2">{TITLE}</h4>
@@ -214,7 +202,6 @@ export default function CultivationManager({ accountId, onRefresh: _onRefresh }:
             ) : (
               <div className="space-y-2">
                 {typeCultivations.map(cultivation => {
-                  const cost = CultivationCalculator.calculateCost(cultivation.current_level, cultivation.target_level, cultivation.mode, cultivation.current_exp)
                   return (
                     <div key={cultivation.id} className="bg-white border border-gray-200 rounded-md p-3 hover:shadow-md transition-shadow">
                       <div className="flex justify-between items-start">
@@ -229,11 +216,11 @@ export default function CultivationManager({ accountId, onRefresh: _onRefresh }:
                           </div>
                           <div>
                             <span className="text-gray-600">经验: </span>
-                            <span className="font-medium text-orange-600">{cost.experience.toLocaleString()}</span>
+                            <span className="font-medium text-orange-600">{cultivation.remaining_exp.toLocaleString()}</span>
                           </div>
                           <div>
                             <span className="text-gray-600">金钱: </span>
-                            <span className="font-medium text-green-600">{cost.money.toLocaleString()}</span>
+                            <span className="font-medium text-green-600">{cultivation.remaining_money.toLocaleString()}</span>
                           </div>
                           <div className="col-span-4 text-xs text-gray-500">
                             {(() => {
@@ -265,8 +252,8 @@ export default function CultivationManager({ accountId, onRefresh: _onRefresh }:
                 {typeCultivations.length > 0 && (
                   <div className="bg-yellow-50 border border-yellow-200 rounded-md p-2 text-sm">
                     <span className="text-yellow-900 font-medium">总计: </span>
-                    <span className="text-orange-700">经验 {totals.experience.toLocaleString()} / </span>
-                    <span className="text-green-700">金钱 {totals.money.toLocaleString()}</span>
+                    <span className="text-orange-700">经验 {totals.cultivation_exp.toLocaleString()} / </span>
+                    <span className="text-green-700">金钱 {totals.gold.toLocaleString()}</span>
                   </div>
                 )}
               </div>
@@ -1,25 +1,13 @@
-import SkillCalculator from '../utils/skillCalculator'
-
-interface Skill {
-  id: number
-  account_id: number
-  skill_name: string
-  current_level: number
-  target_level: number
-}
+import type { SkillWithCost } from '../services/accountService'
 
 interface Props {
-  skill: Skill
+  skill: SkillWithCost
   onEdit: () => void
   onDelete: () => void
   isMaster?: boolean
 }
 
 export default function SkillItem({ skill, onEdit, onDelete, isMaster }: Props) {
-  const cost = isMaster
-    ? SkillCalculator.calculateMasterCost(skill.current_level, skill.target_level)
-    : SkillCalculator.calculateAssistCost(skill.current_level, skill.target_level)
-
   return (
     <div className="bg-white border border-gray-200 rounded-md p-4 hover:shadow-md transition-shadow">
       <div className="flex justify-between items-start">
@@ -36,16 +24,16 @@ export default function SkillItem({ skill, onEdit, onDelete, isMaster }: Props)
             </div>
             <div>
               <span className="text-gray-600">所需经验: </span>
-              <span className="font-medium text-orange-600">{cost.experience.toLocaleString()}</span>
+              <span className="font-medium text-orange-600">{skill.remaining_exp.toLocaleString()}</span>
             </div>
             <div>
               <span className="text-gray-600">所需金钱: </span>
-              <span className="font-medium text-green-600">{cost.money.toLocaleString()}</span>
+              <span className="font-medium text-green-600">{skill.remaining_money.toLocaleString()}</span>
             </div>
             {!isMaster && (
               <div className="col-span-2">
                 <span className="text-gray-600">所需帮贡: </span>
-                <span className="font-medium">{(cost as any).gang?.toLocaleString?.() ?? (cost as any).gang}</span>
+                <span className="font-medium">{skill.remaining_gang.toLocaleString()}</span>
               </div>
             )}
           </div>
@@ -9,13 +9,13 @@ import {
   addMasterSkill,
   updateMasterSkill,
   deleteMasterSkill,
-  getMasterSkills,
   addAssistSkill,
   updateAssistSkill,
   deleteAssistSkill,
-  getAssistSkills,
   Skill,
 } from '../services/skillService'
+import { getAccountBundle, SkillWithCost } from '../services/accountService'
+import type { ResourceAmounts } from '../services/changeLogService'
 
 interface Props {
   accountId: number
@@ -31,7 +31,8 @@ interface FormState {
 }
 
 export default function SkillManager({ accountId, school, type, onRefresh: _onRefresh }: Props) {
-  const [skills, setSkills] = useState<Skill[]>([])
+  const [skills, setSkills] = useState<SkillWithCost[]>([])
+  const [totals, setTotals] = useState<ResourceAmounts>({ exp: 0, gold: 0, gang: 0, cultivation_exp: 0 })
   const [showForm, setShowForm] = useState(false)
   const [editingSkill, setEditingSkill] = useState<Skill | null>(null)
   const [confirmDeleteId, setConfirmDeleteId] = useState<number | null>(null)
@@ -52,8 +53,10 @@ export default function SkillManager({ accountId, school, type, onRefresh: _onRe
 
   const loadSkills = async () => {
     try {
-      const result = isMaster ? await getMasterSkills(accountId) : await getAssistSkills(accountId)
-      setSkills(result)
+      // 每行的剩余消耗与合计都取自后端同一快照
+      const bundle = await getAccountBundle(accountId)
+      setSkills(isMaster ? bundle.master_skills : bundle.assist_skills)
+      setTotals(isMaster ? bundle.remaining.master : bundle.remaining.assist)
     } catch (error) {
       console.error('加载技能失败:', error)
     }
@@ -112,7 +115,6 @@ export default function SkillManager({ accountId, school, type, onRefresh: _onRe
         }
       }
       await loadSkills()
-      window.dispatchEvent(new Event('mhxy:dataChanged'))
       setShowForm(false)
       setEditingSkill(null)
     } catch (error) {
@@ -129,31 +131,13 @@ export default function SkillManager({ accountId, school, type, onRefresh: _onRe
         await deleteAssistSkill(id)
       }
       await loadSkills()
-      window.dispatchEvent(new Event('mhxy:dataChanged'))
-      showToast('删除成功', 'success')
+      showToast('已移入回收站', 'success')
     } catch (error) {
       console.error('删除技能失败:', error)
       showToast('删除技能失败', 'error')
     }
   }
 
-  const getTotalCost = () => {
-    let totalExp = 0
-    let totalMoney = 0
-    let totalGang = 0
-    skills.forEach(skill => {
-      const cost = isMaster
-        ? SkillCalculator.calculateMasterCost(skill.current_level, skill.target_level)
-        : SkillCalculator.calculateAssistCost(skill.current_level, skill.target_level)
-      totalExp += cost.experience
-      totalMoney += cost.money
-      if (!isMaster) totalGang += cost.gang || 0
-    })
-    return { experience: totalExp, money: totalMoney, gang: totalGang }
-  }
-
-  const totals = getTotalCost()
-
   return (
     <div>
       <div className="flex justify-between items-center mb-4">
@@ -289,14 +273,14 @@ export default function SkillManager({ accountId, school, type, onRefresh: _onRe
       {skills.length > 0 && (
         <div className="mt-4 bg-yellow-50 border border-yellow-200 rounded-md p-3">
           <p className="text-sm text-yellow-900">
-            总计所需经验: <span className="font-bold">{totals.experience.toLocaleString()}</span>
+            总计所需经验: <span className="font-bold">{totals.exp.toLocaleString()}</span>
           </p>
           <p className="text-sm text-yellow-900">
-            总计所需金钱: <span className="font-bold">{totals.money.toLocaleString()}</span>
+            总计所需金钱: <span className="font-bold">{totals.gold.toLocaleString()}</span>
           </p>
           {!isMaster && (
             <p className="text-sm text-yellow-900">
-              总计所需帮贡: <span className="font-bold">{(totals as any).gang.toLocaleString()}</span>
+              总计所需帮贡: <span className="font-bold">{totals.gang.toLocaleString()}</span>
             </p>
           )}
         </div>
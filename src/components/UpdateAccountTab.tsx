@@ -1,14 +1,11 @@
-import { useEffect, useMemo, useState } from 'react'
+import { useEffect, useState } from 'react'
 import { School } from '../data/schools'
-import { getMasterSkills, getAssistSkills, updateMasterSkill, updateAssistSkill, Skill } from '../services/skillService'
-import { getCultivations as getCultivationsService, updateCultivation as updateCultivationService, Cultivation } from '../services/cultivationService'
-import SkillCalculator from '../utils/skillCalculator'
-import CultivationCalculator from '../utils/cultivationCalculator'
-import { cultivationPerLevel } from '../data/cultivationCosts'
+import { getMasterSkills, getAssistSkills, Skill } from '../services/skillService'
+import { getCultivations as getCultivationsService, Cultivation } from '../services/cultivationService'
 import Button from './ui/button'
-import { addSpendLog } from '../services/spendService'
-import { addChangeLog } from '../services/changeLogService'
+import { applyUpgrades, quoteUpgrades, isInsufficientBalance, UpgradeItem, UpgradeQuote } from '../services/changeLogService'
 import { showToast } from './ui/toast'
+import { planUpgrades, PlanObjective } from '../services/planService'
 
 interface Props { accountId: number; school: School | null; onUpdated: () => void }
 
@@ -20,174 +17,100 @@ export default function UpdateAccountTab({ accountId, school: _school, onUpdated
   const [aNew, setANew] = useState<Record<number, string>>({})
   const [cNewLvl, setCNewLvl] = useState<Record<number, string>>({})
   const [cNewExp, setCNewExp] = useState<Record<number, string>>({})
+  const [budgetWan, setBudgetWan] = useState('')
+  const [objective, setObjective] = useState<PlanObjective>('levels')
+  const [quote, setQuote] = useState<UpgradeQuote | null>(null)
+  const [quoteError, setQuoteError] = useState('')
 
-  useEffect(() => {
-    const load = async () => {
-      const [ms, as, cs] = await Promise.all([
-        getMasterSkills(accountId),
-        getAssistSkills(accountId),
-        getCultivationsService(accountId),
-      ])
-      setMaster(ms)
-      setAssist(as)
-      setCultivations(cs as Cultivation[])
-    }
-    load()
-  }, [accountId])
+  const load = async () => {
+    const [ms, as, cs] = await Promise.all([
+      getMasterSkills(accountId),
+      getAssistSkills(accountId),
+      getCultivationsService(accountId),
+    ])
+    setMaster(ms)
+    setAssist(as)
+    setCultivations(cs as Cultivation[])
+  }
 
-  const totalMoney = useMemo(() => {
-    let sum = 0
+  useEffect(() => { load() }, [accountId])
+
+  // 输入框中高于当前的新等级/经验，作为一组升级交给后端计算与写入
+  const buildItems = (): UpgradeItem[] => {
+    const items: UpgradeItem[] = []
     master.forEach(s => {
       const nv = parseInt(mNew[s.id] || '')
-      if (!isNaN(nv) && nv > s.current_level) {
-        sum += SkillCalculator.calculateMasterCost(s.current_level, nv).money
-      }
+      if (!isNaN(nv) && nv > s.current_level) items.push({ category: 'master', id: s.id, to_level: nv })
     })
     assist.forEach(s => {
       const nv = parseInt(aNew[s.id] || '')
-      if (!isNaN(nv) && nv > s.current_level) {
-        sum += SkillCalculator.calculateAssistCost(s.current_level, nv).money
-      }
+      if (!isNaN(nv) && nv > s.current_level) items.push({ category: 'assist', id: s.id, to_level: nv })
     })
     cultivations.forEach(c => {
       const nl = parseInt(cNewLvl[c.id] || '')
-      const neRaw = cNewExp[c.id]
-      const ne = neRaw !== undefined && neRaw !== '' ? parseInt(neRaw) : NaN
-      const unit = c.mode === '2w' ? 20000 : 30000
+      const ne = parseInt(cNewExp[c.id] || '')
       if (!isNaN(nl) && nl > c.current_level) {
         // 跨等级升级：如果新经验为空，默认为0
-        const newExpForCalc = !isNaN(ne) ? ne : 0
-        if (nl > c.current_level + 1) {
-          // 跨等级升级：先计算到新等级-1的满经验
-          const costToPrevLevel = CultivationCalculator.calculateCost(c.current_level, nl - 1, c.mode, c.current_exp)
-          // 获取 nl-1 级的满经验值
-          const prevLevelMaxExp = cultivationPerLevel[c.mode][nl - 1]?.exp || 0
-          // 从 nl-1 级满经验升到 nl 级，nl 级经验为 newExpForCalc
-          // 计算从 nl-1 级满经验到 nl 级满经验的消耗
-          const costToNlMax = CultivationCalculator.calculateCost(nl - 1, nl, c.mode, prevLevelMaxExp)
-          // 如果新经验小于 nl 级满经验，需要减去从 newExpForCalc 到满经验的部分
-          const nlLevelMaxExp = cultivationPerLevel[c.mode][nl]?.exp || 0
-          if (newExpForCalc < nlLevelMaxExp) {
-            // 计算从 nl 级 newExpForCalc 到 nl 级满经验的消耗（手动计算）
-            const excessExp = nlLevelMaxExp - newExpForCalc
-            const excessSteps = Math.ceil(excessExp / 10)
-            const excessMoney = excessSteps * unit
-            sum += costToPrevLevel.money + costToNlMax.money - excessMoney
-          } else {
-            sum += costToPrevLevel.money + costToNlMax.money
-          }
-        } else {
-          // 只升一级，使用新经验（如果为空则为0）
-          const costWithNewExp = CultivationCalculator.calculateCost(c.current_level, nl, c.mode, !isNaN(ne) ? ne : 0)
-          sum += costWithNewExp.money
-        }
+        items.push({ category: 'cultivation', id: c.id, to_level: nl, to_exp: !isNaN(ne) ? ne : 0 })
       } else if (!isNaN(ne) && ne > c.current_exp) {
-        const inc = Math.floor((ne - c.current_exp) / 10)
-        if (inc > 0) sum += inc * unit
+        items.push({ category: 'cultivation', id: c.id, to_level: c.current_level, to_exp: ne })
       }
     })
-    return sum
-  }, [master, assist, cultivations, mNew, aNew, cNewLvl, cNewExp])
+    return items
+  }
 
-  const handleApply = async () => {
+  // 预计消耗由后端按今天生效的消耗表计算，与写入时一致
+  useEffect(() => {
+    const items = buildItems()
+    if (items.length === 0) { setQuote(null); setQuoteError(''); return }
+    let stale = false
+    quoteUpgrades(accountId, items)
+      .then(q => { if (!stale) { setQuote(q); setQuoteError('') } })
+      .catch(e => { if (!stale) { setQuote(null); setQuoteError(String(e)) } })
+    return () => { stale = true }
+  }, [accountId, master, assist, cultivations, mNew, aNew, cNewLvl, cNewExp])
+
+  const rowMoney = (category: UpgradeItem['category'], id: number) =>
+    quote?.items.find(it => it.category === category && it.id === id)?.cost.gold || 0
+  const totalMoney = quote?.total.gold || 0
+
+  // 按预算（万）规划各项新等级并填入输入框，确认后仍通过“应用更新并记录”写入
+  const handlePlan = async () => {
+    const wan = parseInt(budgetWan)
+    if (isNaN(wan) || wan <= 0) { showToast('请输入预算', 'error'); return }
     try {
-      // accumulate updates
-      let money = 0
-      const today = new Date().toISOString().slice(0,10)
-      for (const s of master) {
-        const nv = parseInt(mNew[s.id] || '')
-        if (!isNaN(nv) && nv > s.current_level) {
-          money += SkillCalculator.calculateMasterCost(s.current_level, nv).money
-          await updateMasterSkill(s.id, nv, s.target_level)
-          const cost = SkillCalculator.calculateMasterCost(s.current_level, nv)
-          await addChangeLog({
-            account_id: accountId, category: 'master', name: s.skill_name,
-            from_level: s.current_level, to_level: nv, from_exp: undefined, to_exp: undefined,
-            consumed_exp: cost.experience, consumed_money: cost.money, consumed_gang: 0, consumed_cultivation_exp: 0, date: today,
-          })
-        }
+      const plan = await planUpgrades(accountId, { budget: { gold: wan * 10000 }, objective })
+      const m: Record<number, string> = {}, a: Record<number, string> = {}, c: Record<number, string> = {}
+      for (const it of plan.items) {
+        const target = it.category === 'master' ? m : it.category === 'assist' ? a : c
+        target[it.id] = String(it.to_level)
       }
-      for (const s of assist) {
-        const nv = parseInt(aNew[s.id] || '')
-        if (!isNaN(nv) && nv > s.current_level) {
-          money += SkillCalculator.calculateAssistCost(s.current_level, nv).money
-          await updateAssistSkill(s.id, nv, s.target_level)
-          const cost = SkillCalculator.calculateAssistCost(s.current_level, nv)
-          await addChangeLog({
-            account_id: accountId, category: 'assist', name: s.skill_name,
-            from_level: s.current_level, to_level: nv, from_exp: undefined, to_exp: undefined,
-            consumed_exp: cost.experience, consumed_money: cost.money, consumed_gang: cost.gang || 0, consumed_cultivation_exp: 0, date: today,
-          })
-        }
-      }
-      for (const c of cultivations) {
-        const nlRaw = cNewLvl[c.id]
-        const nl = nlRaw !== undefined && nlRaw !== '' ? parseInt(nlRaw) : NaN
-        const neRaw = cNewExp[c.id]
-        const ne = neRaw !== undefined && neRaw !== '' ? parseInt(neRaw) : NaN
-        const unit = c.mode === '2w' ? 20000 : 30000
-        if (!isNaN(nl) && nl > c.current_level) {
-          // 跨等级升级：如果新经验为空，默认为0
-          const newExpForSave = !isNaN(ne) ? ne : 0
-          let cost: { experience: number, money: number }
-          
-          if (nl > c.current_level + 1) {
-            // 跨等级升级：先计算到新等级-1的满经验
-            const costToPrevLevel = CultivationCalculator.calculateCost(c.current_level, nl - 1, c.mode, c.current_exp)
-            // 获取 nl-1 级的满经验值
-            const prevLevelMaxExp = cultivationPerLevel[c.mode][nl - 1]?.exp || 0
-            // 从 nl-1 级满经验升到 nl 级，nl 级经验为 newExpForSave
-            const costToNlMax = CultivationCalculator.calculateCost(nl - 1, nl, c.mode, prevLevelMaxExp)
-            // 如果新经验小于 nl 级满经验，需要减去从 newExpForSave 到满经验的部分
-            const nlLevelMaxExp = cultivationPerLevel[c.mode][nl]?.exp || 0
-            if (newExpForSave < nlLevelMaxExp) {
-              const excessExp = nlLevelMaxExp - newExpForSave
-              const excessSteps = Math.ceil(excessExp / 10)
-              const excessMoney = excessSteps * unit
-              const excessExpTotal = excessExp
-              cost = {
-                experience: costToPrevLevel.experience + costToNlMax.experience - excessExpTotal,
-                money: costToPrevLevel.money + costToNlMax.money - excessMoney
-              }
-            } else {
-              cost = {
-                experience: costToPrevLevel.experience + costToNlMax.experience,
-                money: costToPrevLevel.money + costToNlMax.money
-              }
-            }
-          } else {
-            // 只升一级，使用新经验（如果为空则为0）
-            cost = CultivationCalculator.calculateCost(c.current_level, nl, c.mode, newExpForSave)
-          }
-          
-          money += cost.money
-          await updateCultivationService(c.id, c.mode, newExpForSave, nl, c.target_level)
-          await addChangeLog({
-            account_id: accountId, category: 'cultivation', name: c.name || (c.mode==='2w'?'防御修炼':'攻击修炼'),
-            from_level: c.current_level, to_level: nl, from_exp: c.current_exp, to_exp: newExpForSave,
-            consumed_exp: 0, consumed_money: cost.money, consumed_gang: 0, consumed_cultivation_exp: cost.experience, date: today,
-          })
-        } else if (!isNaN(ne) && ne > c.current_exp) {
-          const inc = Math.floor((ne - c.current_exp) / 10)
-          if (inc > 0) money += inc * unit
-          await updateCultivationService(c.id, c.mode, ne, c.current_level, c.target_level)
-          await addChangeLog({
-            account_id: accountId, category: 'cultivation', name: c.name || (c.mode==='2w'?'防御修炼':'攻击修炼'),
-            from_level: c.current_level, to_level: c.current_level, from_exp: c.current_exp, to_exp: ne,
-            consumed_exp: 0, consumed_money: inc * unit, consumed_gang: 0, consumed_cultivation_exp: inc * 10, date: today,
-          })
-        }
-      }
-      if (money > 0) {
-        await addSpendLog(accountId, money, today, '更新技能自动记录')
+      setMNew(m); setANew(a); setCNewLvl(c); setCNewExp({})
+      if (plan.items.length === 0) showToast('预算不足以提升任何一级', 'error')
+    } catch (e) {
+      console.error(e)
+      showToast(String(e), 'error')
+    }
+  }
+
+  // 等级、变更记录与扣费在后端同一事务内完成；余额不足时询问是否仍然记录（允许余额为负）
+  const handleApply = async () => {
+    const items = buildItems()
+    if (items.length === 0) return
+    try {
+      try {
+        await applyUpgrades(accountId, items)
+      } catch (e) {
+        if (!isInsufficientBalance(e) || !window.confirm(`${String(e)}\n是否仍然记录？`)) throw e
+        await applyUpgrades(accountId, items, true)
       }
       showToast('已更新并记录消耗', 'success')
       onUpdated()
-      window.dispatchEvent(new Event('mhxy:dataChanged'))
       setMNew({}); setANew({}); setCNewLvl({}); setCNewExp({})
+      await load()
     } catch (e) {
       console.error(e)
-      showToast('更新失败', 'error')
+      showToast(`更新失败：${String(e)}`, 'error')
     }
   }
 
@@ -196,6 +119,14 @@ export default function UpdateAccountTab({ accountId, school: _school, onUpdated
   return (
     <div className="bg-white border border-gray-200 rounded-md p-3">
       <h3 className="text-base font-semibold pl-3 mb-3">更新技能</h3>
+      <div className="flex items-center gap-2 pl-3 mb-3">
+        <input className="w-28 border border-gray-300 rounded px-2 py-1 text-sm" inputMode="numeric" value={budgetWan} onChange={(e)=>{const v=e.target.value; if(/^\d*$/.test(v)) setBudgetWan(v)}} placeholder="预算（万）" />
+        <select className="border border-gray-300 rounded px-2 py-1 text-sm" value={objective} onChange={(e)=>setObjective(e.target.value as PlanObjective)}>
+          <option value="levels">升级数最多</option>
+          <option value="targets">达成目标最多</option>
+        </select>
+        <Button size="sm" onClick={handlePlan}>按预算规划</Button>
+      </div>
       <div className="grid grid-cols-1 md:grid-cols-2 gap-3">
         <div>
           <h4 className="text-sm font-semibold pl-3 mb-2">师门技能</h4>
@@ -233,43 +164,9 @@ export default function UpdateAccountTab({ accountId, school: _school, onUpdated
                 <input className="w-24 border border-gray-300 rounded px-2 py-1 text-sm" inputMode="numeric" value={cNewLvl[c.id] || ''} onChange={(e)=>{const v=e.target.value; if(/^\d*$/.test(v)) setCNewLvl(prev=>({...prev,[c.id]:v}))}} placeholder="新等级" />
                 <input className="w-28 border border-gray-300 rounded px-2 py-1 text-sm" inputMode="numeric" value={cNewExp[c.id] || ''} onChange={(e)=>{const v=e.target.value; if(/^\d*$/.test(v)) setCNewExp(prev=>({...prev,[c.id]:v}))}} placeholder="新当前经验" />
               </div>
-              {(() => {
-                const nlRaw = cNewLvl[c.id]
-                const nl = nlRaw !== undefined && nlRaw !== '' ? parseInt(nlRaw) : NaN
-                const neRaw = cNewExp[c.id]
-                const ne = neRaw !== undefined && neRaw !== '' ? parseInt(neRaw) : NaN
-                const unit = c.mode === '2w' ? 20000 : 30000
-                let money = 0
-                if (!isNaN(nl) && nl > c.current_level) {
-                  // 跨等级升级：如果新经验为空，默认为0
-                  const newExpForCalc = !isNaN(ne) ? ne : 0
-                  if (nl > c.current_level + 1) {
-                    // 跨等级升级：先计算到新等级-1的满经验
-                    const costToPrevLevel = CultivationCalculator.calculateCost(c.current_level, nl - 1, c.mode, c.current_exp)
-                    const prevLevelMaxExp = cultivationPerLevel[c.mode][nl - 1]?.exp || 0
-                    const costToNlMax = CultivationCalculator.calculateCost(nl - 1, nl, c.mode, prevLevelMaxExp)
-                    const nlLevelMaxExp = cultivationPerLevel[c.mode][nl]?.exp || 0
-                    if (newExpForCalc < nlLevelMaxExp) {
-                      const excessExp = nlLevelMaxExp - newExpForCalc
-                      const excessSteps = Math.ceil(excessExp / 10)
-                      const excessMoney = excessSteps * unit
-                      money = costToPrevLevel.money + costToNlMax.money - excessMoney
-                    } else {
-                      money = costToPrevLevel.money + costToNlMax.money
-                    }
-                  } else {
-                    // 只升一级，使用新经验（如果为空则为0）
-                    const cost = CultivationCalculator.calculateCost(c.current_level, nl, c.mode, newExpForCalc)
-                    money = cost.money
-                  }
-                } else if (!isNaN(ne) && ne > c.current_exp) {
-                  const inc = Math.floor((ne - c.current_exp) / 10)
-                  if (inc > 0) money = inc * unit
-                }
-                return money > 0 ? (
-                  <div className="mt-2 text-xs text-emerald-700">预计消耗：{Math.floor(money/10000)} 万</div>
-                ) : null
-              })()}
+              {rowMoney('cultivation', c.id) > 0 && (
+                <div className="mt-2 text-xs text-emerald-700">预计消耗：{Math.floor(rowMoney('cultivation', c.id)/10000)} 万</div>
+              )}
             </div>
           ))}
           {cultivations.length===0 && <div className="text-xs text-gray-500 pl-3">暂无修炼</div>}
@@ -277,8 +174,10 @@ export default function UpdateAccountTab({ accountId, school: _school, onUpdated
       </div>
 
       <div className="mt-4 flex items-center justify-between">
-        <div className="text-sm text-gray-700 pl-3">预计消耗金钱：<span className="font-semibold text-emerald-700">{toWanInt(totalMoney)} 万</span></div>
-        <Button size="sm" onClick={handleApply} disabled={totalMoney<=0}>应用更新并记录</Button>
+        {quoteError
+          ? <div className="text-sm text-red-600 pl-3">{quoteError}</div>
+          : <div className="text-sm text-gray-700 pl-3">预计消耗金钱：<span className="font-semibold text-emerald-700">{toWanInt(totalMoney)} 万</span></div>}
+        <Button size="sm" onClick={handleApply} disabled={!quote || quoteError !== ''}>应用更新并记录</Button>
       </div>
     </div>
   )
@@ -23,7 +23,6 @@ export default function UpdateTab({ accounts, onUpdated }: Props) {
       setAmount('')
       setNote('')
       onUpdated()
-      window.dispatchEvent(new Event('mhxy:dataChanged'))
     } catch (e) {
       console.error(e)
       showToast('记录失败', 'error')
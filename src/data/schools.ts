@@ -193,7 +193,7 @@ export const schools: School[] = [
       '运思如泉',
       '擎天之械',
       '千机奇巧',
-      '攻云以守'
+      '探奥索隐'
     ]
   },
   {
@@ -1,5 +1,7 @@
 // 累计到该等级所需的总经验与总师门金钱（从0升到该等级的合计）。
 // 你可以把完整的 1-180 级表粘贴到这里，格式见下方示例。
+// 后端计算不读取本文件：完整表请写成数据目录下的 cost_tables.json / cost_tables.toml，
+// 通过 import_cost_tables 导入（master.form 填 cumulative 或 per_level）。
 
 export type CumulativeSkillCost = Record<number, { exp: number; money: number }>
 
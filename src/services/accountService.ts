@@ -1,4 +1,7 @@
 import { invoke } from '@tauri-apps/api/core'
+import type { ChangeLog, ResourceAmounts } from './changeLogService'
+import type { Skill } from './skillService'
+import type { Cultivation } from './cultivationService'
 
 export interface Account {
   id: number
@@ -7,19 +10,66 @@ export interface Account {
   level: number
   experience: number
   gold: number
+  gang: number
+  cultivation_exp: number
+  server_type: string
 }
 
-export async function initDb(): Promise<void> {
-  await invoke('db_init')
+export interface RuleProfile {
+  server_type: string
+  label: string
+  max_skill_level: number
+  skill_cap_offset: number
+  max_cultivation_level: number
+  cost_version: string
+  cost_effective_date: string
+}
+
+export async function listRuleProfiles(): Promise<RuleProfile[]> {
+  return await invoke('list_rule_profiles') as RuleProfile[]
+}
+
+// 返回初始化时不影响启动的问题（如自定义消耗表被忽略）
+export async function initDb(): Promise<string[]> {
+  return await invoke('db_init') as string[]
+}
+
+export interface NewAccountCultivation {
+  name: string
+  type: 'person' | 'pet'
+  mode: '2w' | '3w'
+  current_exp?: number
+  current_level?: number
+  target_level?: number
+}
+
+export interface AddAccountOptions {
+  // 默认 normal（普通服）
+  serverType?: string
+  gold?: number
+  // 传入时在同一事务中写入该门派全部师门技能
+  masterSkills?: { current_level: number; target_level: number }
+  cultivations?: NewAccountCultivation[]
 }
 
 export async function addAccount(
   name: string,
   school: string,
   level: number,
-  experience: number
+  experience: number,
+  options: AddAccountOptions = {}
 ): Promise<number> {
-  return await invoke('add_account', { name, school, level, experience }) as number
+  const account = {
+    name,
+    school,
+    level,
+    experience,
+    server_type: options.serverType,
+    gold: options.gold,
+    master_skills: options.masterSkills,
+    cultivations: options.cultivations,
+  }
+  return await invoke('add_account', { account }) as number
 }
 
 export async function updateAccount(
@@ -27,9 +77,10 @@ export async function updateAccount(
   name: string,
   school: string,
   level: number,
-  experience: number
+  experience: number,
+  serverType?: string
 ): Promise<void> {
-  await invoke('update_account', { id, name, school, level, experience })
+  await invoke('update_account', { id, name, school, level, experience, serverType })
 }
 
 export async function deleteAccount(id: number): Promise<void> {
@@ -40,3 +91,115 @@ export async function getAllAccounts(): Promise<Account[]> {
   return await invoke('get_all_accounts') as Account[]
 }
 
+
+// 升级经验取自导入的消耗表；没有该级数据时 known 为 false，其余字段为空
+export interface LevelProgress {
+  level: number
+  experience: number
+  known: boolean
+  next_level_exp: number | null
+  exp_to_next: number | null
+  can_level_up: boolean | null
+  progress_percent: number | null
+}
+
+export async function characterExpTable(serverType?: string): Promise<number[]> {
+  return await invoke('character_exp_table', { serverType }) as number[]
+}
+
+export async function getLevelProgress(accountId: number): Promise<LevelProgress> {
+  return await invoke('get_level_progress', { account_id: accountId, accountId }) as LevelProgress
+}
+
+// 由人物等级决定的当前可达上限（目标等级不受此限）
+export interface LevelCaps {
+  server_type: string
+  character_level: number
+  master_skill: number
+  assist_skill: number
+  person_cultivation: number
+  pet_cultivation: number
+}
+
+export async function getLevelCaps(accountId: number): Promise<LevelCaps> {
+  return await invoke('get_level_caps', { account_id: accountId, accountId }) as LevelCaps
+}
+
+export interface Balances {
+  exp: number
+  gold: number
+  gang: number
+  cultivation_exp: number
+}
+
+// 未传入的字段保持不变
+export async function setAccountBalances(
+  accountId: number,
+  balances: { gold?: number; experience?: number; gang?: number; cultivation_exp?: number }
+): Promise<Balances> {
+  return await invoke('set_account_balances', { account_id: accountId, accountId, ...balances }) as Balances
+}
+
+// 到目标等级还需的消耗，与后端计算口径一致
+export interface RemainingCost {
+  account_id: number
+  master: ResourceAmounts
+  assist: ResourceAmounts
+  person_cultivation: ResourceAmounts
+  pet_cultivation: ResourceAmounts
+  total: ResourceAmounts
+}
+
+export interface FleetRemainingCost {
+  accounts: RemainingCost[]
+  total: ResourceAmounts
+}
+
+export async function getAccountRemainingCost(accountId: number): Promise<RemainingCost> {
+  return await invoke('get_account_remaining_cost', { account_id: accountId, accountId }) as RemainingCost
+}
+
+export async function getAllRemainingCosts(taskId?: string): Promise<FleetRemainingCost> {
+  return await invoke('get_all_remaining_costs', { task_id: taskId, taskId }) as FleetRemainingCost
+}
+
+export interface AccountOverview extends Account {
+  remaining: RemainingCost
+  // 目标完成度 0-100
+  completion: number
+  spend_7d: number
+  spend_30d: number
+  last_change_date: string | null
+}
+
+// 全部账号的总览，一次调用返回
+export async function getOverview(taskId?: string): Promise<AccountOverview[]> {
+  return await invoke('get_overview', { task_id: taskId, taskId }) as AccountOverview[]
+}
+
+export interface SkillWithCost extends Skill {
+  remaining_exp: number
+  remaining_money: number
+  remaining_gang: number
+}
+
+export interface CultivationWithCost extends Cultivation {
+  remaining_exp: number
+  remaining_money: number
+}
+
+// 同一读事务内取得的账号详情快照，各行附带到目标等级的剩余消耗
+export interface AccountBundle {
+  account: Account
+  caps: LevelCaps
+  cost_version_id: number | null
+  master_skills: SkillWithCost[]
+  assist_skills: SkillWithCost[]
+  cultivations: CultivationWithCost[]
+  change_logs: ChangeLog[]
+  remaining: RemainingCost
+}
+
+export async function getAccountBundle(accountId: number): Promise<AccountBundle> {
+  return await invoke('get_account_bundle', { account_id: accountId, accountId }) as AccountBundle
+}
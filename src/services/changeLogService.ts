@@ -3,7 +3,7 @@ import { invoke } from '@tauri-apps/api/core'
 export interface ChangeLog {
   id: number
   account_id: number
-  category: 'master' | 'assist' | 'cultivation'
+  category: 'master' | 'assist' | 'cultivation' | 'school'
   name: string
   from_level?: number
   to_level?: number
@@ -15,13 +15,34 @@ export interface ChangeLog {
   consumed_cultivation_exp: number
   date: string
   created_at: string
+  // 记录时生效的消耗表版本，由后端写入
+  cost_version_id?: number | null
+  // 撤销记录所撤销的变更记录 id
+  reverts_id?: number | null
+}
+
+export interface ResourceAmounts {
+  exp: number
+  gold: number
+  gang: number
+  cultivation_exp: number
 }
 
-export async function addChangeLog(payload: Omit<ChangeLog, 'id' | 'created_at'>): Promise<number> {
-  const {
-    account_id, category, name, from_level, to_level, from_exp, to_exp,
-    consumed_exp, consumed_money, consumed_gang, consumed_cultivation_exp, date,
-  } = payload
+export interface ChangeLogRecompute {
+  change_log_id: number
+  recorded_version_id: number | null
+  recorded: ResourceAmounts
+  current_version_id: number | null
+  current: ResourceAmounts
+  diff: ResourceAmounts
+}
+
+export type NewChangeLog = Pick<ChangeLog, 'account_id' | 'category' | 'name' | 'from_level' | 'to_level' | 'from_exp' | 'to_exp' | 'date'>
+
+// 消耗由后端按记录日期生效的消耗表计算，并同时扣除账号的经验/金钱/帮贡/修炼经验；
+// 余额不足时报错，force 为 true 时允许透支
+export async function addChangeLog(payload: NewChangeLog, force = false): Promise<number> {
+  const { account_id, category, name, from_level, to_level, from_exp, to_exp, date } = payload
   return await invoke('add_change_log', {
     // 兼容 new/old 参数命名
     account_id, accountId: account_id,
@@ -30,16 +51,58 @@ export async function addChangeLog(payload: Omit<ChangeLog, 'id' | 'created_at'>
     to_level, toLevel: to_level,
     from_exp, fromExp: from_exp,
     to_exp, toExp: to_exp,
-    consumed_exp, consumedExp: consumed_exp,
-    consumed_money, consumedMoney: consumed_money,
-    consumed_gang, consumedGang: consumed_gang,
-    consumed_cultivation_exp, consumedCultivationExp: consumed_cultivation_exp,
     date,
+    force,
   }) as number
 }
 
+// 一项升级；修炼的 to_exp 为升级后的当前经验，省略为 0
+export interface UpgradeItem {
+  category: 'master' | 'assist' | 'cultivation'
+  id: number
+  to_level: number
+  to_exp?: number
+}
+
+export interface UpgradeCost {
+  category: UpgradeItem['category']
+  id: number
+  name: string
+  cost: ResourceAmounts
+}
+
+export interface UpgradeQuote {
+  cost_version_id: number | null
+  items: UpgradeCost[]
+  total: ResourceAmounts
+}
+
+// 按今天生效的消耗表计算一组升级的消耗（不写入），与 applyUpgrades 的结果一致
+export async function quoteUpgrades(accountId: number, items: UpgradeItem[]): Promise<UpgradeQuote> {
+  return await invoke('quote_upgrades', { accountId, items }) as UpgradeQuote
+}
+
+// 在同一事务内更新等级、写入变更记录并扣除消耗，任一项失败则全部不生效；返回变更记录 id
+export async function applyUpgrades(accountId: number, items: UpgradeItem[], force = false): Promise<number[]> {
+  return await invoke('apply_upgrades', { accountId, items, force }) as number[]
+}
+
 export async function getChangeLogs(accountId: number): Promise<ChangeLog[]> {
   return await invoke('get_change_logs', { account_id: accountId, accountId }) as ChangeLog[]
 }
 
 
+
+// 按今天生效的消耗表重新计算该次升级的消耗
+export async function recomputeChangeLog(id: number): Promise<ChangeLogRecompute> {
+  return await invoke('recompute_change_log', { id }) as ChangeLogRecompute
+}
+
+// 恢复到升级前等级并返还消耗（未扣过余额的早期记录不返还），返回新写入的撤销记录 id；之后同一技能/修炼还有变更时报错
+export async function revertChangeLog(id: number): Promise<number> {
+  return await invoke('revert_change_log', { id }) as number
+}
+
+export function isInsufficientBalance(error: unknown): boolean {
+  return String(error).startsWith('余额不足')
+}
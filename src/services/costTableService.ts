@@ -0,0 +1,104 @@
+import { invoke } from '@tauri-apps/api/core'
+
+export interface CostTableVersion {
+  id: number
+  server_type: string
+  version: string
+  effective_date: string
+  source: 'builtin' | 'custom'
+  active: boolean
+  created_at: string
+}
+
+export interface SkillLevelCost {
+  exp: number
+  money: number
+  gang: number
+}
+
+// 第 n 项为升到 n+1 级的单级消耗
+export interface CostTable {
+  master: SkillLevelCost[]
+  assist: SkillLevelCost[]
+  person_cultivation_exp: number[]
+  pet_cultivation_exp: number[]
+  cultivation_prices: { mode: string; price: number }[]
+  // 人物升级经验，未导入时为内置表
+  character_exp: number[]
+}
+
+export async function listCostTables(serverType?: string): Promise<CostTableVersion[]> {
+  return await invoke('list_cost_tables', { server_type: serverType, serverType }) as CostTableVersion[]
+}
+
+export async function getCostTable(id: number): Promise<CostTable> {
+  return await invoke('get_cost_table', { id }) as CostTable
+}
+
+export interface CostFileReport {
+  ok: boolean
+  errors: string[]
+  warnings: string[]
+}
+
+export interface CostImportResult {
+  report: CostFileReport
+  version_id: number | null
+  file: string | null
+}
+
+// 导入 json/toml 自定义消耗表；校验未通过时 version_id 为空，原因见 report
+export async function importCostTables(path: string, taskId?: string): Promise<CostImportResult> {
+  return await invoke('import_cost_tables', { path, task_id: taskId, taskId }) as CostImportResult
+}
+
+// 不传 path 时校验数据目录下当前使用的自定义文件
+export async function validateCostTables(path?: string): Promise<CostFileReport> {
+  return await invoke('validate_cost_tables', { path }) as CostFileReport
+}
+
+// 删除自定义文件并恢复内置表，返回停用的自定义版本数
+export async function resetCostTables(): Promise<number> {
+  return await invoke('reset_cost_tables') as number
+}
+
+export interface LevelRow {
+  level: number
+  exp: number
+  money: number
+  gang: number
+  cum_exp?: number
+  cum_money?: number
+  cum_gang?: number
+}
+
+export interface ConvertInput {
+  rows: Partial<LevelRow>[]
+  // 不填时自动识别
+  form?: 'per_level' | 'cumulative'
+  kind?: 'master' | 'assist' | 'person_cultivation' | 'pet_cultivation'
+  // 修炼表：每 10 点经验的价格与 money 列单位，用于核对 money 列
+  price?: number
+  money_unit?: number
+}
+
+export interface Inconsistency {
+  level: number
+  column: string
+  expected: number
+  actual: number
+  message: string
+}
+
+export interface ConvertReport {
+  detected_form: 'per_level' | 'cumulative'
+  reason: string
+  per_level: LevelRow[]
+  cumulative: LevelRow[]
+  issues: Inconsistency[]
+}
+
+// 累计表与单级表互转，并报告不一致的行
+export async function convertCostTable(input: ConvertInput): Promise<ConvertReport> {
+  return await invoke('convert_cost_table', { input }) as ConvertReport
+}
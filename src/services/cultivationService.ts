@@ -9,6 +9,7 @@ export interface Cultivation {
   current_exp: number
   current_level: number
   target_level: number
+  pet_id?: number | null
 }
 
 export async function addCultivation(
@@ -18,7 +19,8 @@ export async function addCultivation(
   mode: '2w' | '3w',
   currentExp: number,
   currentLevel: number,
-  targetLevel: number
+  targetLevel: number,
+  petId?: number
 ): Promise<number> {
   return await invoke('add_cultivation', {
     // 兼容参数命名
@@ -33,9 +35,15 @@ export async function addCultivation(
     currentLevel,
     target_level: targetLevel,
     targetLevel,
+    pet_id: petId,
+    petId,
   }) as number
 }
 
+export async function setCultivationPet(id: number, petId: number | null): Promise<void> {
+  await invoke('set_cultivation_pet', { id, pet_id: petId, petId })
+}
+
 export async function updateCultivation(
   id: number,
   mode: '2w' | '3w',
@@ -67,3 +75,24 @@ export async function getCultivations(accountId: number): Promise<Cultivation[]>
   return await invoke('get_cultivations', { account_id: accountId, accountId }) as Cultivation[]
 }
 
+
+export interface CultivationWithCost extends Cultivation {
+  remaining_exp: number
+  remaining_money: number
+}
+
+export interface CultivationGroup {
+  rows: CultivationWithCost[]
+  total_level: number
+  remaining_exp: number
+  remaining_money: number
+}
+
+export interface CultivationSummary {
+  person: CultivationGroup
+  pet: CultivationGroup
+}
+
+export async function getCultivationSummary(accountId: number): Promise<CultivationSummary> {
+  return await invoke('get_cultivation_summary', { account_id: accountId, accountId }) as CultivationSummary
+}
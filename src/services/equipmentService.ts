@@ -0,0 +1,45 @@
+import { invoke } from '@tauri-apps/api/core'
+
+export type EquipmentSlot = 'weapon' | 'helmet' | 'necklace' | 'armour' | 'belt' | 'shoes'
+
+export interface Equipment {
+  id: number
+  account_id: number
+  slot: EquipmentSlot
+  name: string
+  level: number
+  main_stats: string
+  special: string
+  gem_level: number
+  gold_spent: number
+  upgrade_spent: number
+  notes?: string | null
+  created_at: string
+}
+
+export interface EquipmentInput {
+  slot: EquipmentSlot
+  name: string
+  level?: number
+  main_stats?: string
+  special?: string
+  gem_level?: number
+  gold_spent?: number
+  notes?: string
+}
+
+export async function addEquipment(accountId: number, equipment: EquipmentInput): Promise<number> {
+  return await invoke('add_equipment', { accountId, equipment }) as number
+}
+
+export async function updateEquipment(id: number, equipment: EquipmentInput): Promise<void> {
+  await invoke('update_equipment', { id, equipment })
+}
+
+export async function deleteEquipment(id: number): Promise<void> {
+  await invoke('delete_equipment', { id })
+}
+
+export async function getEquipment(accountId: number): Promise<Equipment[]> {
+  return await invoke('get_equipment', { accountId }) as Equipment[]
+}
@@ -0,0 +1,19 @@
+import { listen, UnlistenFn } from '@tauri-apps/api/event'
+
+// 后端在写操作成功后向所有窗口广播，多个窗口据此同步刷新
+export type DataEntity =
+  | 'account' | 'master_skill' | 'assist_skill' | 'cultivation' | 'pet' | 'equipment'
+  | 'spend_log' | 'change_log' | 'school_change' | 'cost_table'
+
+export interface DataChanged {
+  entity: DataEntity
+  // 账号、技能、修炼的 delete 为移入回收站，restore / purge 为从回收站恢复 / 彻底删除
+  action: 'create' | 'update' | 'delete' | 'restore' | 'purge'
+  // 不属于某个账号（如消耗表）时为空
+  account_id: number | null
+  id: number | null
+}
+
+export async function onDataChanged(cb: (e: DataChanged) => void): Promise<UnlistenFn> {
+  return await listen<DataChanged>('mhxy://data-changed', e => cb(e.payload))
+}
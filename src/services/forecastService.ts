@@ -0,0 +1,47 @@
+import { invoke } from '@tauri-apps/api/core'
+
+// 每天可投入的梦幻币
+export interface GoldRate {
+  pessimistic: number
+  average: number
+  optimistic: number
+}
+
+// 按该速度无法完成时为 null
+export interface DateBand {
+  optimistic: string | null
+  average: string | null
+  pessimistic: string | null
+}
+
+export interface TargetForecast {
+  category: 'master' | 'assist' | 'cultivation'
+  id: number
+  name: string
+  current_level: number
+  target_level: number
+  remaining_gold: number
+  cumulative_gold: number
+  eta: DateBand
+}
+
+export interface AccountForecast {
+  account_id: number
+  account_name: string
+  gold_balance: number
+  window_start: string
+  window_end: string
+  observed_days: number
+  observed_total: number
+  // 窗口内有消耗的天数；少于 7 天时 low_confidence 为 true，速度仅供参考
+  active_days: number
+  low_confidence: boolean
+  rate: GoldRate
+  items: TargetForecast[]
+  all_targets: DateBand
+}
+
+// 以近 days 天（默认 90）的消耗记录估算速度；不传 accountId 时返回全部账号
+export async function forecastTargets(accountId?: number, days?: number, taskId?: string): Promise<AccountForecast[]> {
+  return await invoke('forecast_targets', { account_id: accountId, accountId, days, task_id: taskId, taskId }) as AccountForecast[]
+}
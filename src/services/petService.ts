@@ -0,0 +1,38 @@
+import { invoke } from '@tauri-apps/api/core'
+
+export interface Pet {
+  id: number
+  account_id: number
+  name: string
+  species: string
+  level: number
+  loyalty: number
+  skills: string[]
+  notes?: string | null
+  created_at: string
+}
+
+export interface PetInput {
+  name: string
+  species?: string
+  level?: number
+  loyalty?: number
+  skills?: string[]
+  notes?: string
+}
+
+export async function addPet(accountId: number, pet: PetInput): Promise<number> {
+  return await invoke('add_pet', { accountId, pet }) as number
+}
+
+export async function updatePet(id: number, pet: PetInput): Promise<void> {
+  await invoke('update_pet', { id, pet })
+}
+
+export async function deletePet(id: number): Promise<void> {
+  await invoke('delete_pet', { id })
+}
+
+export async function getPets(accountId: number): Promise<Pet[]> {
+  return await invoke('get_pets', { accountId }) as Pet[]
+}
@@ -0,0 +1,75 @@
+import { invoke } from '@tauri-apps/api/core'
+import type { ResourceAmounts } from './changeLogService'
+
+export type PlanObjective = 'levels' | 'weighted' | 'targets'
+
+// 不填的预算项不限制
+export interface PlanBudget {
+  gold: number
+  exp?: number | null
+  gang?: number | null
+  cultivation_exp?: number | null
+}
+
+export interface PlanRequest {
+  budget: PlanBudget
+  objective?: PlanObjective
+  // 键为技能/修炼名称或类别（master / assist / cultivation），名称优先，默认 1，0 表示不参与
+  weights?: Record<string, number>
+  within_targets?: boolean
+}
+
+export interface PlanItem {
+  category: 'master' | 'assist' | 'cultivation'
+  id: number
+  name: string
+  from_level: number
+  to_level: number
+  target_level: number
+  reached_target: boolean
+  cost: ResourceAmounts
+}
+
+export interface UpgradePlan {
+  objective: PlanObjective
+  budget: PlanBudget
+  items: PlanItem[]
+  total: ResourceAmounts
+  remaining: PlanBudget
+  levels_gained: number
+  targets_reached: number
+  score: number
+}
+
+// 只计算不写库，结果可填入更新页后再确认
+export async function planUpgrades(accountId: number, request: PlanRequest, taskId?: string): Promise<UpgradePlan> {
+  return await invoke('plan_upgrades', { account_id: accountId, accountId, request, task_id: taskId, taskId }) as UpgradePlan
+}
+
+// 不填的资源不限制，至少给出一项
+export interface Available {
+  gold?: number | null
+  exp?: number | null
+  gang?: number | null
+  cultivation_exp?: number | null
+}
+
+export interface Reach {
+  category: PlanItem['category']
+  id: number
+  name: string
+  from_level: number
+  from_exp: number
+  level: number
+  // 修炼在 level 级内已积累的经验
+  exp: number
+  max_level: number
+  limited_by: 'budget' | 'max_level'
+  spent: ResourceAmounts
+  leftover: Available
+}
+
+// 单个技能/修炼用现有资源最多能升到哪一级
+export async function reachWithBudget(category: PlanItem['category'], id: number, available: Available): Promise<Reach> {
+  return await invoke('reach_with_budget', { category, id, available }) as Reach
+}
@@ -6,17 +6,23 @@ export interface SpendLog {
   amount: number
   date: string
   note?: string
+  pet_id?: number | null
+  equipment_id?: number | null
   created_at: string
 }
 
 export interface SpendSummary { date: string; total: number }
 
-export async function addSpendLog(accountId: number, amount: number, date: string, note?: string): Promise<number> {
-  return await invoke('add_spend_log', { account_id: accountId, accountId, amount, date, note }) as number
+export interface SpendLink { petId?: number; equipmentId?: number }
+
+export async function addSpendLog(accountId: number, amount: number, date: string, note?: string, link: SpendLink = {}): Promise<number> {
+  const { petId, equipmentId } = link
+  return await invoke('add_spend_log', { account_id: accountId, accountId, amount, date, note, pet_id: petId, petId, equipment_id: equipmentId, equipmentId }) as number
 }
 
-export async function getSpendLogs(accountId: number, start?: string, end?: string): Promise<SpendLog[]> {
-  return await invoke('get_spend_logs', { account_id: accountId, accountId, start, end }) as SpendLog[]
+export async function getSpendLogs(accountId: number, start?: string, end?: string, link: SpendLink = {}): Promise<SpendLog[]> {
+  const { petId, equipmentId } = link
+  return await invoke('get_spend_logs', { account_id: accountId, accountId, start, end, pet_id: petId, petId, equipment_id: equipmentId, equipmentId }) as SpendLog[]
 }
 
 export async function getSpendSummaryDaily(start: string, end: string): Promise<SpendSummary[]> {
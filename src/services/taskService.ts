@@ -0,0 +1,30 @@
+import { invoke } from '@tauri-apps/api/core'
+import { listen, UnlistenFn } from '@tauri-apps/api/event'
+
+// 长操作（导入消耗表、规划、预测、总览、修复日期）可传入 taskId，通过进度事件跟踪并用 cancelTask 取消
+export interface TaskProgress {
+  task_id: string
+  stage: string
+  done: number
+  total: number
+}
+
+// 取消后操作以该错误信息结束
+export const TASK_CANCELLED = '操作已取消'
+
+let seq = 0
+export function newTaskId(): string {
+  seq += 1
+  return `task-${Date.now()}-${seq}`
+}
+
+export async function onTaskProgress(taskId: string, cb: (p: TaskProgress) => void): Promise<UnlistenFn> {
+  return await listen<TaskProgress>('mhxy://progress', e => {
+    if (e.payload.task_id === taskId) cb(e.payload)
+  })
+}
+
+// 任务已结束时返回 false
+export async function cancelTask(taskId: string): Promise<boolean> {
+  return await invoke('cancel_task', { task_id: taskId, taskId }) as boolean
+}
@@ -0,0 +1,37 @@
+import { invoke } from '@tauri-apps/api/core'
+
+// 删除的账号、技能、修炼先进入回收站，超过保留天数后自动彻底删除
+export type TrashCategory = 'account' | 'master' | 'assist' | 'cultivation'
+
+export interface TrashItem {
+  category: TrashCategory
+  id: number
+  account_id: number
+  account_name: string
+  name: string
+  deleted_at: string
+  // 未开启自动清理时为空
+  purge_at: string | null
+}
+
+export async function listTrash(accountId?: number): Promise<TrashItem[]> {
+  return await invoke('list_trash', { account_id: accountId, accountId }) as TrashItem[]
+}
+
+// 技能与修炼需先恢复所属账号
+export async function restoreFromTrash(category: TrashCategory, id: number): Promise<void> {
+  await invoke('restore_from_trash', { category, id })
+}
+
+export async function purgeFromTrash(category: TrashCategory, id: number): Promise<void> {
+  await invoke('purge_from_trash', { category, id })
+}
+
+export async function getTrashRetentionDays(): Promise<number> {
+  return await invoke('get_trash_retention_days') as number
+}
+
+// 0 表示不自动清理；返回因新设置立即清理的记录数
+export async function setTrashRetentionDays(days: number): Promise<number> {
+  return await invoke('set_trash_retention_days', { days }) as number
+}